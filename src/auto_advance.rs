@@ -0,0 +1,84 @@
+use crate::{SectionFrag, TextBox, clear_section};
+use bevy::prelude::*;
+use bevy_pretty_text::prelude::*;
+
+/// Clears sections automatically once they have finished scrolling.
+///
+/// As a resource, this applies to every [`TextBox`]. Inserted on a [`TextBox`], it
+/// overrides the resource for that box.
+///
+/// The delay is `base + per_char * len`, unless the section was given an explicit delay
+/// with [`SectionFragExt::auto_advance`](crate::SectionFragExt::auto_advance).
+#[derive(Resource, Component, Debug, Clone, Copy)]
+pub struct AutoAdvance {
+    pub enabled: bool,
+    pub base: f32,
+    pub per_char: f32,
+}
+
+impl Default for AutoAdvance {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            base: 1.,
+            per_char: 0.04,
+        }
+    }
+}
+
+impl AutoAdvance {
+    pub fn enabled() -> Self {
+        Self {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    fn delay(&self, len: usize) -> f32 {
+        self.base + self.per_char * len as f32
+    }
+}
+
+#[derive(Component)]
+pub(crate) struct AutoAdvanceTimer {
+    delay: Option<f32>,
+    len: usize,
+    elapsed: f32,
+}
+
+impl AutoAdvanceTimer {
+    pub fn new(frag: &SectionFrag, section: &TypeWriterSection) -> Self {
+        Self {
+            delay: frag.auto_advance,
+            len: section.text.chars().count(),
+            elapsed: 0.,
+        }
+    }
+}
+
+pub(crate) fn auto_advance_sections(
+    mut commands: Commands,
+    time: Res<Time>,
+    global: Res<AutoAdvance>,
+    textboxes: Query<(&Children, Option<&AutoAdvance>), With<TextBox>>,
+    mut sections: Query<(&mut AutoAdvanceTimer, &OnClear), With<AwaitClear>>,
+) {
+    for (children, auto_advance) in textboxes.iter() {
+        let auto_advance = auto_advance.unwrap_or(&global);
+        if !auto_advance.enabled {
+            continue;
+        }
+
+        for child in children.iter() {
+            let Ok((mut timer, on_clear)) = sections.get_mut(child) else {
+                continue;
+            };
+
+            timer.elapsed += time.delta_secs();
+            let delay = timer.delay.unwrap_or_else(|| auto_advance.delay(timer.len));
+            if timer.elapsed >= delay {
+                clear_section(&mut commands, child, on_clear);
+            }
+        }
+    }
+}
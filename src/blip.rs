@@ -0,0 +1,135 @@
+use crate::{Speaker, TextBox};
+use bevy::{audio::Volume, platform::collections::HashMap, prelude::*};
+use bevy_pretty_text::prelude::*;
+use std::{borrow::Cow, ops::RangeInclusive};
+
+/// A sound played as a section's glyphs are revealed.
+///
+/// Insert on a [`TextBox`] to give every section in the box a voice, or register it
+/// per speaker in [`SpeakerBlips`]. Whitespace and punctuation are never voiced, and
+/// sections skipped with [`FinishTextbox`](crate::FinishTextbox) stay silent.
+#[derive(Component, Debug, Clone)]
+pub struct Blip {
+    pub source: Handle<AudioSource>,
+    /// Playback speed of each blip is picked from this range.
+    pub pitch: RangeInclusive<f32>,
+    /// Plays on every `every`th voiced glyph.
+    pub every: usize,
+    pub volume: f32,
+}
+
+impl Blip {
+    pub fn new(source: Handle<AudioSource>) -> Self {
+        Self {
+            source,
+            pitch: 0.9..=1.1,
+            every: 2,
+            volume: 1.,
+        }
+    }
+
+    pub fn with_pitch(mut self, pitch: RangeInclusive<f32>) -> Self {
+        self.pitch = pitch;
+        self
+    }
+
+    pub fn with_every(mut self, every: usize) -> Self {
+        self.every = every.max(1);
+        self
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+}
+
+/// Per-speaker [`Blip`]s, which take precedence over the [`TextBox`]'s.
+#[derive(Resource, Default)]
+pub struct SpeakerBlips(HashMap<Speaker, Blip>);
+
+impl SpeakerBlips {
+    pub fn with(mut self, speaker: impl Into<Cow<'static, str>>, blip: Blip) -> Self {
+        self.insert(speaker, blip);
+        self
+    }
+
+    pub fn insert(&mut self, speaker: impl Into<Cow<'static, str>>, blip: Blip) {
+        self.0.insert(Speaker::new(speaker), blip);
+    }
+
+    pub fn get(&self, speaker: &Speaker) -> Option<&Blip> {
+        self.0.get(speaker)
+    }
+}
+
+/// Reveal progress of a section, removed when the section is finished early.
+#[derive(Component, Default)]
+pub(crate) struct BlipState {
+    revealed: usize,
+    voiced: usize,
+}
+
+pub(crate) fn play_blips(
+    mut commands: Commands,
+    speaker_blips: Res<SpeakerBlips>,
+    textboxes: Query<&Blip, With<TextBox>>,
+    mut sections: Query<(
+        &ChildOf,
+        &TypeWriterSection,
+        &Scroll,
+        Option<&Speaker>,
+        &mut BlipState,
+    )>,
+    mut seed: Local<u32>,
+) {
+    for (child_of, section, scroll, speaker, mut state) in sections.iter_mut() {
+        let revealed = scroll.index();
+        if revealed <= state.revealed {
+            continue;
+        }
+
+        let blip = speaker
+            .and_then(|speaker| speaker_blips.get(speaker))
+            .or_else(|| textboxes.get(child_of.parent()).ok());
+        let Some(blip) = blip else {
+            state.revealed = revealed;
+            continue;
+        };
+
+        let mut play = false;
+        for glyph in section
+            .text
+            .chars()
+            .skip(state.revealed)
+            .take(revealed - state.revealed)
+        {
+            if glyph.is_alphanumeric() {
+                play |= state.voiced % blip.every.max(1) == 0;
+                state.voiced += 1;
+            }
+        }
+        state.revealed = revealed;
+
+        if play {
+            commands.spawn((
+                AudioPlayer::new(blip.source.clone()),
+                PlaybackSettings::DESPAWN
+                    .with_speed(random_in(&mut seed, &blip.pitch))
+                    .with_volume(Volume::Linear(blip.volume)),
+            ));
+        }
+    }
+}
+
+fn random_in(seed: &mut u32, range: &RangeInclusive<f32>) -> f32 {
+    // xorshift32, more than enough to vary pitch.
+    let mut x = seed.wrapping_add(0x9E37_79B9);
+    x ^= x << 13;
+    x ^= x >> 17;
+    x ^= x << 5;
+    *seed = x;
+
+    let t = x as f32 / u32::MAX as f32;
+    range.start() + (range.end() - range.start()) * t
+}
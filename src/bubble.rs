@@ -0,0 +1,110 @@
+use crate::{TextBox, TextBoxMode};
+use bevy::prelude::*;
+
+/// Turns a [`TextBoxMode::Ui`] textbox into a speech bubble that follows `target`.
+///
+/// The bubble is positioned above the target's [`GlobalTransform`], as seen through
+/// `camera`, and kept inside the camera's viewport. Leave the textbox node's size as
+/// [`Val::Auto`] so that its background fits the current section, wrapping past
+/// `max_width`.
+///
+/// ```ignore
+/// commands.spawn((
+///     TextBox::ui(TextFont::from_font_size(16.)),
+///     SpeechBubble::new(npc, camera).with_offset(Vec3::Y * 40.),
+///     ImageNode::new(asset_server.load("bubble.png")).with_mode(NodeImageMode::Sliced(slicer)),
+/// ));
+/// ```
+#[derive(Component, Debug, Clone, Copy)]
+#[require(Node = Node { position_type: PositionType::Absolute, ..Default::default() })]
+pub struct SpeechBubble {
+    pub target: Entity,
+    pub camera: Entity,
+    /// World space offset from the target's origin.
+    pub offset: Vec3,
+    pub max_width: f32,
+    /// Minimum distance from the edges of the viewport.
+    pub margin: f32,
+}
+
+impl SpeechBubble {
+    pub fn new(target: Entity, camera: Entity) -> Self {
+        Self {
+            target,
+            camera,
+            offset: Vec3::ZERO,
+            max_width: 300.,
+            margin: 8.,
+        }
+    }
+
+    pub fn with_offset(mut self, offset: Vec3) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_max_width(mut self, max_width: f32) -> Self {
+        self.max_width = max_width;
+        self
+    }
+
+    pub fn with_margin(mut self, margin: f32) -> Self {
+        self.margin = margin;
+        self
+    }
+}
+
+pub(crate) fn position_speech_bubbles(
+    mut bubbles: Query<(
+        &SpeechBubble,
+        &TextBox,
+        &mut Node,
+        &ComputedNode,
+        &mut Visibility,
+    )>,
+    targets: Query<&GlobalTransform>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    ui_scale: Res<UiScale>,
+) {
+    for (bubble, textbox, mut node, computed, mut visibility) in bubbles.iter_mut() {
+        if textbox.mode() != TextBoxMode::Ui {
+            warn_once!("`SpeechBubble` requires a `TextBoxMode::Ui` textbox");
+            continue;
+        }
+
+        let projected = cameras
+            .get(bubble.camera)
+            .ok()
+            .zip(targets.get(bubble.target).ok())
+            .and_then(|((camera, camera_transform), target)| {
+                let position = camera
+                    .world_to_viewport(camera_transform, target.translation() + bubble.offset)
+                    .ok()?;
+                Some((position, camera.logical_viewport_rect()?))
+            });
+        let Some((position, viewport)) = projected else {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        };
+        visibility.set_if_neq(Visibility::Inherited);
+
+        // Node positions are scaled by `UiScale`, so work in unscaled units. The projected
+        // position already includes the viewport's offset.
+        let position = position / ui_scale.0;
+        let viewport = Rect::from_corners(viewport.min / ui_scale.0, viewport.max / ui_scale.0);
+        let size = computed.size() * computed.inverse_scale_factor();
+
+        let min = viewport.min + bubble.margin;
+        let max = (viewport.max - size - bubble.margin).max(min);
+        let top_left = (position - Vec2::new(size.x / 2., size.y)).clamp(min, max);
+
+        let left = Val::Px(top_left.x);
+        let top = Val::Px(top_left.y);
+        let max_width = Val::Px(bubble.max_width);
+        if node.left != left || node.top != top || node.max_width != max_width {
+            node.left = left;
+            node.top = top;
+            node.max_width = max_width;
+        }
+    }
+}
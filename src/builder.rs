@@ -0,0 +1,113 @@
+use bevy::prelude::*;
+use bevy_pretty_text::prelude::*;
+
+/// Builds a [`TypeWriterSection`] from spans, pauses and effects, as an alternative to the
+/// `s!` macro for sections generated at run time.
+///
+/// ```ignore
+/// let section = SectionBuilder::new()
+///     .text("Hello")
+///     .pause(0.5)
+///     .text(", ")
+///     .effect(TextEffect::Wave, "World")
+///     .color(Color::srgb(0., 1., 0.), "!");
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct SectionBuilder {
+    text: String,
+    len: usize,
+    commands: Vec<IndexedCommand>,
+    effects: Vec<IndexedTextEffect>,
+    colors: Vec<IndexedTextColor>,
+}
+
+impl SectionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends plain text.
+    pub fn text(mut self, text: impl AsRef<str>) -> Self {
+        self.push(text.as_ref());
+        self
+    }
+
+    /// Appends text displayed with `effect`.
+    pub fn effect(self, effect: TextEffect, text: impl AsRef<str>) -> Self {
+        self.effects([effect], text)
+    }
+
+    /// Appends text displayed with all of `effects`.
+    pub fn effects(
+        self,
+        effects: impl IntoIterator<Item = TextEffect>,
+        text: impl AsRef<str>,
+    ) -> Self {
+        self.span(effects, None, text)
+    }
+
+    /// Appends text displayed in `color`.
+    pub fn color(self, color: impl Into<Color>, text: impl AsRef<str>) -> Self {
+        self.span([], Some(color.into()), text)
+    }
+
+    /// Appends text displayed with all of `effects`, and in `color` if there is one.
+    pub(crate) fn span(
+        mut self,
+        effects: impl IntoIterator<Item = TextEffect>,
+        color: Option<Color>,
+        text: impl AsRef<str>,
+    ) -> Self {
+        let start = self.len;
+        self.push(text.as_ref());
+        let end = self.len;
+        self.effects
+            .extend(
+                effects
+                    .into_iter()
+                    .map(|effect| IndexedTextEffect { start, end, effect }),
+            );
+        if let Some(color) = color {
+            self.colors.push(IndexedTextColor { start, end, color });
+        }
+        self
+    }
+
+    /// Pauses the scroll for `seconds` before the next glyph.
+    pub fn pause(self, seconds: f32) -> Self {
+        self.command(TypeWriterCommand::Delay(seconds))
+    }
+
+    /// Multiplies the scroll speed of the following glyphs.
+    pub fn speed(self, speed: f32) -> Self {
+        self.command(TypeWriterCommand::Speed(speed))
+    }
+
+    /// Runs `command` before the next glyph.
+    pub fn command(mut self, command: TypeWriterCommand) -> Self {
+        self.commands.push(IndexedCommand {
+            index: self.len,
+            command,
+        });
+        self
+    }
+
+    pub fn build(self) -> TypeWriterSection {
+        let mut section = TypeWriterSection::from(self.text);
+        section.commands = self.commands.into();
+        section.effects = self.effects.into();
+        section.colors = self.colors.into();
+        section
+    }
+
+    fn push(&mut self, text: &str) {
+        self.text.push_str(text);
+        self.len += text.chars().count();
+    }
+}
+
+impl From<SectionBuilder> for TypeWriterSection {
+    fn from(value: SectionBuilder) -> Self {
+        value.build()
+    }
+}
@@ -0,0 +1,511 @@
+use crate::{
+    IntoLine, LineEnd, SectionFrag, TextBox, TextBoxEntity, TextBoxMode, TextboxPlayer,
+    end_fragment,
+    input::{CursorPosition, TextboxButtons},
+    is_last_fragment, lifecycle,
+};
+use bevy::{prelude::*, render::primitives::Aabb};
+use bevy_sequence::{fragment::DataLeaf, prelude::*};
+use std::{borrow::Cow, sync::Arc};
+
+/// Presents a list of options to the player, each continuing into its own branch.
+///
+/// The fragment ends once the selected branch ends, so the surrounding sequence resumes
+/// after the choice.
+///
+/// ```ignore
+/// let frag = (
+///     "Will you help me?".speaker("Nic"),
+///     ChoiceFrag::new([
+///         ChoiceOption::new("Sure.").then("Thank you!"),
+///         ChoiceOption::new("Pay him off.")
+///             .then("Pleasure doing business.")
+///             .enabled_if(|world: &World| world.resource::<Gold>().0 >= 10),
+///         ChoiceOption::new("No."),
+///     ]),
+///     "Anyway...",
+/// );
+/// ```
+#[derive(Clone)]
+pub struct ChoiceFrag {
+    textbox: Entity,
+    options: Vec<ChoiceOption>,
+    branch: bool,
+    line_end: Option<LineEnd>,
+}
+
+impl ChoiceFrag {
+    pub fn new(options: impl IntoIterator<Item = ChoiceOption>) -> Self {
+        Self {
+            textbox: Entity::PLACEHOLDER,
+            options: options.into_iter().collect(),
+            branch: false,
+            line_end: None,
+        }
+    }
+}
+
+impl IntoLine for ChoiceFrag {
+    fn with_line_end(self, line_end: LineEnd) -> Self {
+        Self {
+            line_end: Some(line_end),
+            ..self
+        }
+    }
+}
+
+crate::impl_textbox_frag!(ChoiceFrag);
+
+type Condition = Arc<dyn Fn(&World) -> bool + Send + Sync>;
+
+#[derive(Clone)]
+struct Branch(Arc<dyn Fn(Entity, ChoiceEnd, &mut Commands) + Send + Sync>);
+
+/// Ends a [`ChoiceFrag`] once its selected option, and that option's branch, ends.
+#[derive(Clone)]
+struct ChoiceEnd {
+    textbox: Entity,
+    fragment: FragmentId,
+    end: FragmentEndEvent,
+    line_end: Option<LineEnd>,
+    /// Whether the choice is the last fragment of its sequence.
+    last: bool,
+}
+
+impl ChoiceEnd {
+    fn new(event: &FragmentEvent<ChoiceFrag>, last: bool) -> Self {
+        Self {
+            textbox: event.data.textbox,
+            fragment: event.id,
+            end: event.end(),
+            line_end: event.data.line_end.clone(),
+            last: !event.data.branch && last,
+        }
+    }
+
+    /// Ends the choice, finishing its sequence if it is `last`.
+    fn finish(&self, commands: &mut Commands) {
+        if self.last {
+            lifecycle::finish_sequence(commands, self.textbox, self.fragment);
+        }
+        end_fragment(commands, self.fragment, self.end, self.line_end.as_ref());
+    }
+}
+
+#[derive(Clone)]
+pub struct ChoiceOption {
+    text: Cow<'static, str>,
+    /// Localization key resolved in place of the text.
+    #[cfg(feature = "fluent")]
+    key: Option<Cow<'static, str>>,
+    branch: Option<Branch>,
+    visible: Option<Condition>,
+    enabled: Option<Condition>,
+}
+
+impl ChoiceOption {
+    pub fn new(text: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            text: text.into(),
+            #[cfg(feature = "fluent")]
+            key: None,
+            branch: None,
+            visible: None,
+            enabled: None,
+        }
+    }
+
+    /// Creates an option whose text is the message `key` in the active
+    /// [`Localization`](crate::Localization) locale.
+    ///
+    /// The key itself is shown if the message is missing from every locale.
+    #[cfg(feature = "fluent")]
+    pub fn localized(key: impl Into<Cow<'static, str>>) -> Self {
+        let key = key.into();
+        Self {
+            key: Some(key.clone()),
+            ..Self::new(key)
+        }
+    }
+
+    /// Plays `branch` in the same [`TextBox`] when this option is selected.
+    pub fn then<F>(mut self, branch: F) -> Self
+    where
+        F: IntoFragment<SectionFrag, TextBoxEntity> + Clone + Send + Sync + 'static,
+    {
+        self.branch = Some(Branch(Arc::new(move |textbox, mut end, commands| {
+            // The branch finishes the sequence in place of the choice.
+            let last = std::mem::take(&mut end.last);
+            let frag = branch
+                .clone()
+                .always()
+                .once()
+                .on_end(move |mut commands: Commands| end.finish(&mut commands));
+            spawn_root_with_context(frag, TextBoxEntity::nested(textbox, last), commands);
+        })));
+        self
+    }
+
+    /// Hides this option unless `condition` holds when the choice is presented.
+    pub fn visible_if(
+        mut self,
+        condition: impl Fn(&World) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.visible = Some(Arc::new(condition));
+        self
+    }
+
+    /// Shows this option disabled unless `condition` holds when the choice is presented.
+    pub fn enabled_if(
+        mut self,
+        condition: impl Fn(&World) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.enabled = Some(Arc::new(condition));
+        self
+    }
+
+    /// The text of this option, or its localization key.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The text shown for this option.
+    #[cfg_attr(not(feature = "fluent"), allow(unused_variables))]
+    fn resolve_text(&self, world: &World) -> Cow<'static, str> {
+        #[cfg(feature = "fluent")]
+        if let Some(text) = self
+            .key
+            .as_ref()
+            .and_then(|key| crate::localization::localize_text(key, world))
+        {
+            return text.into();
+        }
+        self.text.clone()
+    }
+}
+
+/// Sent when the player selects an option of a [`ChoiceFrag`].
+#[derive(Event, Debug, Clone)]
+pub struct ChoiceSelected {
+    pub textbox: Entity,
+    pub index: usize,
+    pub text: Cow<'static, str>,
+}
+
+/// Colors applied to the options of a [`ChoiceFrag`].
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct ChoiceColors {
+    pub normal: Color,
+    pub selected: Color,
+    pub disabled: Color,
+}
+
+impl Default for ChoiceColors {
+    fn default() -> Self {
+        Self {
+            normal: Color::WHITE,
+            selected: Color::srgb(1., 0.85, 0.2),
+            disabled: Color::srgba(1., 1., 1., 0.4),
+        }
+    }
+}
+
+/// Moves next to the selected option of a [`ChoiceFrag`].
+///
+/// Must be a child of a sprite [`TextBox`]. Only the vertical position is changed.
+#[derive(Component)]
+pub struct ChoiceCursor;
+
+#[derive(Component)]
+pub(crate) struct ChoiceList {
+    options: Vec<ChoiceOption>,
+    entries: Vec<ChoiceEntry>,
+    selected: usize,
+    end: ChoiceEnd,
+}
+
+struct ChoiceEntry {
+    entity: Entity,
+    option: usize,
+    enabled: bool,
+    /// The text shown for the option.
+    text: Cow<'static, str>,
+}
+
+impl ChoiceList {
+    /// Resolves the text of localized options again with `localize`.
+    #[cfg(feature = "fluent")]
+    pub(crate) fn relocalize(
+        &mut self,
+        localize: impl Fn(&str) -> Option<String>,
+        texts: &mut Query<AnyOf<(&mut Text2d, &mut Text)>>,
+    ) {
+        let ChoiceList {
+            options, entries, ..
+        } = self;
+        for entry in entries.iter_mut() {
+            let Some(text) = options[entry.option].key.as_deref().and_then(&localize) else {
+                continue;
+            };
+            if let Ok((text_2d, node_text)) = texts.get_mut(entry.entity) {
+                if let Some(mut text_2d) = text_2d {
+                    text_2d.0.clone_from(&text);
+                }
+                if let Some(mut node_text) = node_text {
+                    node_text.0.clone_from(&text);
+                }
+            }
+            entry.text = text.into();
+        }
+    }
+}
+
+pub(crate) fn spawn_choice_frags(
+    world: &World,
+    mut commands: Commands,
+    mut reader: EventReader<FragmentEvent<ChoiceFrag>>,
+    textboxes: Query<&TextBox>,
+    parents: Query<&ChildOf>,
+    children: Query<&Children>,
+) {
+    for event in reader.read() {
+        let textbox = event.data.textbox;
+        let end = ChoiceEnd::new(
+            event,
+            is_last_fragment(event.id.entity(), &parents, &children),
+        );
+        let Ok(textbox_data) = textboxes.get(textbox) else {
+            warn!("choice played in a missing textbox, skipping");
+            end.finish(&mut commands);
+            continue;
+        };
+
+        let options = event.data.options.clone();
+        let list = match textbox_data.mode {
+            TextBoxMode::Sprite => commands.spawn((Transform::default(), Visibility::default())),
+            TextBoxMode::Ui => commands.spawn(Node {
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(textbox_data.choice_spacing),
+                ..Default::default()
+            }),
+        }
+        .id();
+        let mut entries = Vec::new();
+        for (i, option) in options.iter().enumerate() {
+            if option
+                .visible
+                .as_ref()
+                .is_some_and(|visible| !visible(world))
+            {
+                continue;
+            }
+
+            let text = option.resolve_text(world);
+            let mut entry = commands.spawn_empty();
+            (textbox_data.bundle.0)(&mut entry);
+            match textbox_data.mode {
+                TextBoxMode::Sprite => entry.insert((
+                    Text2d::new(text.to_string()),
+                    ChoiceOffset(entries.len() as f32 * textbox_data.choice_spacing),
+                )),
+                TextBoxMode::Ui => {
+                    entry.insert((Text::new(text.to_string()), Interaction::default()))
+                }
+            };
+            let entity = entry.id();
+            commands.entity(list).add_child(entity);
+
+            entries.push(ChoiceEntry {
+                entity,
+                option: i,
+                enabled: option.enabled.as_ref().is_none_or(|enabled| enabled(world)),
+                text,
+            });
+        }
+
+        let Some(selected) = entries.iter().position(|entry| entry.enabled) else {
+            warn!("choice has no enabled options, skipping");
+            commands.entity(list).despawn();
+            end.finish(&mut commands);
+            continue;
+        };
+
+        commands.entity(list).insert(ChoiceList {
+            options,
+            entries,
+            selected,
+            end,
+        });
+        commands.entity(textbox).add_child(list);
+    }
+}
+
+/// Stacks options below the position given by the [`TextBox`] bundle.
+#[derive(Component)]
+pub(crate) struct ChoiceOffset(f32);
+
+pub(crate) fn offset_choice_entries(
+    mut commands: Commands,
+    mut entries: Query<(Entity, &ChoiceOffset, &mut Transform)>,
+) {
+    for (entity, offset, mut transform) in entries.iter_mut() {
+        transform.translation.y -= offset.0;
+        commands.entity(entity).remove::<ChoiceOffset>();
+    }
+}
+
+pub(crate) fn navigate_choices(
+    buttons: TextboxButtons,
+    cursor: CursorPosition,
+    mut lists: Query<(&ChildOf, &mut ChoiceList)>,
+    players: Query<(), With<TextboxPlayer>>,
+    sprite_entries: Query<(&GlobalTransform, &Aabb)>,
+    ui_entries: Query<&Interaction, Changed<Interaction>>,
+    mut last_cursor: Local<Option<Vec2>>,
+) {
+    // Only hover with the mouse when it moves so that it does not fight the buttons.
+    let position = cursor.world_position();
+    let cursor = position.filter(|_| position != *last_cursor);
+    *last_cursor = position;
+
+    for (child_of, mut list) in lists.iter_mut() {
+        let textbox = child_of.parent();
+        let up = buttons.just_pressed(textbox, |input| &input.up);
+        let down = buttons.just_pressed(textbox, |input| &input.down);
+        // The mouse belongs to no player.
+        let mouse = !players.contains(textbox);
+
+        let hovered = list.entries.iter().position(|entry| {
+            entry.enabled
+                && mouse
+                && (ui_entries
+                    .get(entry.entity)
+                    .is_ok_and(|interaction| *interaction != Interaction::None)
+                    || cursor.is_some_and(|cursor| {
+                        sprite_entries
+                            .get(entry.entity)
+                            .is_ok_and(|(transform, aabb)| contains(transform, aabb, cursor))
+                    }))
+        });
+        if let Some(hovered) = hovered.filter(|hovered| *hovered != list.selected) {
+            list.selected = hovered;
+        }
+
+        if up != down {
+            let len = list.entries.len();
+            let step = if down { 1 } else { len - 1 };
+            let mut selected = list.selected;
+            for _ in 0..len {
+                selected = (selected + step) % len;
+                if list.entries[selected].enabled {
+                    break;
+                }
+            }
+            if selected != list.selected {
+                list.selected = selected;
+            }
+        }
+    }
+}
+
+fn contains(transform: &GlobalTransform, aabb: &Aabb, point: Vec2) -> bool {
+    let local = transform
+        .affine()
+        .inverse()
+        .transform_point3(point.extend(0.));
+    let offset = (local - Vec3::from(aabb.center)).abs();
+    offset.x <= aabb.half_extents.x && offset.y <= aabb.half_extents.y
+}
+
+pub(crate) fn select_choices(
+    mut commands: Commands,
+    buttons: TextboxButtons,
+    lists: Query<(Entity, &ChildOf, &ChoiceList)>,
+    mut selected_writer: EventWriter<ChoiceSelected>,
+) {
+    for (entity, child_of, list) in lists.iter() {
+        let textbox = child_of.parent();
+        if !buttons.just_pressed(textbox, |input| &input.advance) {
+            continue;
+        }
+
+        let entry = &list.entries[list.selected];
+        let option = &list.options[entry.option];
+
+        selected_writer.write(ChoiceSelected {
+            textbox,
+            index: entry.option,
+            text: entry.text.clone(),
+        });
+        match &option.branch {
+            Some(branch) => (branch.0)(textbox, list.end.clone(), &mut commands),
+            None => list.end.finish(&mut commands),
+        }
+        commands.entity(entity).despawn();
+    }
+}
+
+pub(crate) fn highlight_choices(
+    colors: Res<ChoiceColors>,
+    lists: Query<(&ChildOf, Ref<ChoiceList>)>,
+    textboxes: Query<&Children, With<TextBox>>,
+    mut entries: Query<(&Transform, &mut TextColor), Without<ChoiceCursor>>,
+    mut cursors: Query<(&mut Transform, &mut Visibility), With<ChoiceCursor>>,
+) {
+    for (child_of, list) in lists.iter() {
+        if !list.is_changed() {
+            continue;
+        }
+
+        let mut selected_y = None;
+        for (i, entry) in list.entries.iter().enumerate() {
+            let Ok((transform, mut color)) = entries.get_mut(entry.entity) else {
+                continue;
+            };
+
+            color.0 = if !entry.enabled {
+                colors.disabled
+            } else if i == list.selected {
+                selected_y = Some(transform.translation.y);
+                colors.selected
+            } else {
+                colors.normal
+            };
+        }
+
+        let Ok(children) = textboxes.get(child_of.parent()) else {
+            continue;
+        };
+        for child in children.iter() {
+            if let Ok((mut transform, mut visibility)) = cursors.get_mut(child) {
+                if let Some(y) = selected_y {
+                    transform.translation.y = y;
+                }
+                *visibility = Visibility::Inherited;
+            }
+        }
+    }
+}
+
+pub(crate) fn hide_choice_cursors(
+    mut removed: RemovedComponents<ChoiceList>,
+    lists: Query<&ChildOf, With<ChoiceList>>,
+    textboxes: Query<(Entity, &Children), With<TextBox>>,
+    mut cursors: Query<&mut Visibility, With<ChoiceCursor>>,
+) {
+    if removed.read().count() == 0 {
+        return;
+    }
+
+    for (textbox, children) in textboxes.iter() {
+        if lists.iter().any(|child_of| child_of.parent() == textbox) {
+            continue;
+        }
+
+        for child in children.iter() {
+            if let Ok(mut visibility) = cursors.get_mut(child) {
+                *visibility = Visibility::Hidden;
+            }
+        }
+    }
+}
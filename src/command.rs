@@ -0,0 +1,355 @@
+use crate::variables::{Replacement, replace_section};
+use bevy::{ecs::event::EventRegistry, platform::collections::HashMap, prelude::*};
+use bevy_pretty_text::prelude::*;
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    fmt,
+    sync::{Arc, OnceLock},
+};
+
+/// Commands that writers embed in section text to trigger gameplay mid-line.
+///
+/// A command is written as `{cmd:name}`, or `{cmd:name(arg, ...)}` with comma separated
+/// arguments, and runs as the section's [`Scroll`] reveals the glyph that follows it. Each
+/// name is bound to a system taking its arguments as [`In`], or to an [`Event`] built from
+/// them. Arguments are parsed with [`CommandArgs`], so `{cmd:shake_camera(0.5)}` runs a
+/// system taking `In<(f32,)>`.
+///
+/// ```ignore
+/// app.insert_resource(
+///     InlineCommands::default()
+///         .with("shake_camera", |In((strength,)): In<(f32,)>, mut shake: ResMut<Shake>| {
+///             shake.0 = strength;
+///         })
+///         .with_event("sfx", |(name,): (String,)| PlaySfx(name)),
+/// );
+///
+/// let frag = "Watch this{cmd:shake_camera(0.5)}!";
+/// ```
+#[derive(Resource, Default, Clone)]
+pub struct InlineCommands {
+    handlers: HashMap<Cow<'static, str>, Handler>,
+    /// Whether commands run when their section is finished early with
+    /// [`FinishTextbox`](crate::FinishTextbox).
+    pub skipped: SkippedCommands,
+}
+
+type Handler = Arc<dyn Fn(&mut Commands, &[String]) -> Result<(), CommandError> + Send + Sync>;
+
+/// What happens to the commands of a section that is finished before its [`Scroll`] reaches
+/// them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SkippedCommands {
+    /// Runs the skipped commands at once.
+    #[default]
+    Run,
+    Skip,
+}
+
+impl InlineCommands {
+    pub fn with<A, M>(
+        mut self,
+        name: impl Into<Cow<'static, str>>,
+        system: impl IntoSystem<In<A>, (), M> + Clone + Send + Sync + 'static,
+    ) -> Self
+    where
+        A: CommandArgs,
+        M: 'static,
+    {
+        self.insert(name, system);
+        self
+    }
+
+    pub fn with_event<A, E>(
+        mut self,
+        name: impl Into<Cow<'static, str>>,
+        event: impl Fn(A) -> E + Send + Sync + 'static,
+    ) -> Self
+    where
+        A: CommandArgs,
+        E: Event,
+    {
+        self.insert_event(name, event);
+        self
+    }
+
+    pub fn with_skipped(mut self, skipped: SkippedCommands) -> Self {
+        self.skipped = skipped;
+        self
+    }
+
+    /// Binds `name` to `system`, which is registered the first time the command runs.
+    pub fn insert<A, M>(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        system: impl IntoSystem<In<A>, (), M> + Clone + Send + Sync + 'static,
+    ) where
+        A: CommandArgs,
+        M: 'static,
+    {
+        let registered = Arc::new(OnceLock::new());
+        self.handlers.insert(
+            name.into(),
+            Arc::new(move |commands: &mut Commands, args: &[String]| {
+                let args = A::parse(args)?;
+                let system = system.clone();
+                let registered = registered.clone();
+                commands.queue(move |world: &mut World| {
+                    let id = *registered.get_or_init(|| world.register_system(system));
+                    if let Err(error) = world.run_system_with(id, args) {
+                        error!("{error}");
+                    }
+                });
+                Ok(())
+            }),
+        );
+    }
+
+    /// Binds `name` to the event built by `event`, which is added to the app the first time
+    /// the command runs.
+    pub fn insert_event<A, E>(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        event: impl Fn(A) -> E + Send + Sync + 'static,
+    ) where
+        A: CommandArgs,
+        E: Event,
+    {
+        self.handlers.insert(
+            name.into(),
+            Arc::new(move |commands: &mut Commands, args: &[String]| {
+                let event = event(A::parse(args)?);
+                commands.queue(move |world: &mut World| {
+                    if !world.contains_resource::<Events<E>>() {
+                        EventRegistry::register_event::<E>(world);
+                    }
+                    world.send_event(event);
+                });
+                Ok(())
+            }),
+        );
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.handlers.remove(name).is_some()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.handlers.contains_key(name)
+    }
+}
+
+/// Arguments of an inline command, parsed from the text between its parentheses.
+///
+/// Implemented for `()`, `Vec<String>` and tuples of up to four [`CommandArg`]s.
+pub trait CommandArgs: Sized + Send + Sync + 'static {
+    fn parse(args: &[String]) -> Result<Self, CommandError>;
+}
+
+/// A single argument of an inline command.
+pub trait CommandArg: Sized {
+    fn parse(arg: &str) -> Option<Self>;
+}
+
+macro_rules! impl_command_arg {
+    ($($ty:ty),*) => {
+        $(
+            impl CommandArg for $ty {
+                fn parse(arg: &str) -> Option<Self> {
+                    arg.parse().ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_command_arg!(bool, f32, f64, i32, i64, u32, u64, usize, String);
+
+impl CommandArgs for Vec<String> {
+    fn parse(args: &[String]) -> Result<Self, CommandError> {
+        Ok(args.to_vec())
+    }
+}
+
+macro_rules! impl_command_args {
+    ($len:literal $(, $arg:ident)*) => {
+        impl<$($arg: CommandArg + Send + Sync + 'static),*> CommandArgs for ($($arg,)*) {
+            #[allow(unused_variables, unused_mut)]
+            fn parse(args: &[String]) -> Result<Self, CommandError> {
+                if args.len() != $len {
+                    return Err(CommandError::ArgCount {
+                        expected: $len,
+                        found: args.len(),
+                    });
+                }
+                let mut args = args.iter().enumerate();
+                Ok(($({
+                    let (index, arg) = args.next().unwrap();
+                    $arg::parse(arg).ok_or_else(|| CommandError::InvalidArg {
+                        index,
+                        arg: arg.clone(),
+                    })?
+                },)*))
+            }
+        }
+    };
+}
+
+impl_command_args!(0);
+impl_command_args!(1, A);
+impl_command_args!(2, A, B);
+impl_command_args!(3, A, B, C);
+impl_command_args!(4, A, B, C, D);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    Unknown(String),
+    ArgCount {
+        expected: usize,
+        found: usize,
+    },
+    InvalidArg {
+        index: usize,
+        arg: String,
+    },
+    /// A command at this glyph index is never closed.
+    Unclosed(usize),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown(name) => write!(f, "unknown command `{name}`"),
+            Self::ArgCount { expected, found } => {
+                write!(f, "expected {expected} arguments, found {found}")
+            }
+            Self::InvalidArg { index, arg } => write!(f, "invalid argument {index}: `{arg}`"),
+            Self::Unclosed(index) => write!(f, "unclosed command at glyph {index}"),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// A command removed from a section's text, run when the glyph at `index` is revealed.
+#[derive(Debug, Clone)]
+pub(crate) struct CommandMarker {
+    pub(crate) index: usize,
+    name: String,
+    args: Vec<String>,
+}
+
+/// Removes the commands from `section`, moving its commands, effects and colors along with
+/// the remaining glyphs.
+///
+/// `{{` and `}}` are left for [`Variables`](crate::Variables) to unescape.
+pub(crate) fn extract_commands(
+    section: &TypeWriterSection,
+    world: &World,
+) -> (TypeWriterSection, Vec<CommandMarker>) {
+    if !section.text.contains("{cmd:") {
+        return (section.clone(), Vec::new());
+    }
+
+    let handlers = world.get_resource::<InlineCommands>();
+    let chars = section.text.chars().collect::<Vec<_>>();
+    let mut replacements = Vec::new();
+    let mut markers = Vec::new();
+
+    let mut i = 0;
+    while i < chars.len() {
+        match (chars[i], chars.get(i + 1)) {
+            ('{', Some('{')) | ('}', Some('}')) => i += 2,
+            ('{', _) => {
+                let Some(len) = chars[i + 1..].iter().position(|c| *c == '}') else {
+                    error!(
+                        "{} in section `{}`",
+                        CommandError::Unclosed(i),
+                        section.text
+                    );
+                    break;
+                };
+                let end = i + len + 2;
+                let body = chars[i + 1..end - 1].iter().collect::<String>();
+                if let Some(command) = body.strip_prefix("cmd:") {
+                    let marker = parse_command(command, i);
+                    if handlers.is_none_or(|handlers| !handlers.contains(&marker.name)) {
+                        error!(
+                            "{} in section `{}`",
+                            CommandError::Unknown(marker.name.clone()),
+                            section.text
+                        );
+                    }
+                    markers.push(marker);
+                    replacements.push(Replacement::new(i, end, String::new()));
+                }
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+
+    let mut indices = markers
+        .iter()
+        .map(|marker| marker.index)
+        .collect::<Vec<_>>();
+    let section = replace_section(section, &replacements, &mut indices);
+    for (marker, index) in markers.iter_mut().zip(indices) {
+        marker.index = index;
+    }
+    (section, markers)
+}
+
+/// Parses `name` or `name(arg, ...)`.
+fn parse_command(command: &str, index: usize) -> CommandMarker {
+    let (name, args) = match command
+        .split_once('(')
+        .and_then(|(name, args)| Some((name, args.trim_end().strip_suffix(')')?)))
+    {
+        Some((name, args)) if !args.trim().is_empty() => (
+            name,
+            args.split(',').map(|arg| arg.trim().to_owned()).collect(),
+        ),
+        Some((name, _)) => (name, Vec::new()),
+        None => (command, Vec::new()),
+    };
+    CommandMarker {
+        index,
+        name: name.trim().to_owned(),
+        args,
+    }
+}
+
+/// The commands of a section page that have not run yet, in glyph order.
+#[derive(Component)]
+pub(crate) struct PendingCommands(pub(crate) VecDeque<CommandMarker>);
+
+pub(crate) fn run_inline_commands(
+    mut commands: Commands,
+    handlers: Option<Res<InlineCommands>>,
+    mut sections: Query<(Entity, &Scroll, &mut PendingCommands)>,
+) {
+    for (entity, scroll, mut pending) in sections.iter_mut() {
+        let revealed = scroll.index();
+        while pending
+            .0
+            .front()
+            .is_some_and(|marker| marker.index <= revealed)
+        {
+            let marker = pending.0.pop_front().unwrap();
+            let Some(handler) = handlers
+                .as_ref()
+                .and_then(|handlers| handlers.handlers.get(&*marker.name))
+            else {
+                continue;
+            };
+            if let Err(error) = handler(&mut commands, &marker.args) {
+                error!("{error} in command `{}`", marker.name);
+            }
+        }
+        if pending.0.is_empty() {
+            commands.entity(entity).remove::<PendingCommands>();
+        }
+    }
+}
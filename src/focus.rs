@@ -0,0 +1,171 @@
+use crate::{TextBox, TextboxInput, TextboxPlayer};
+use bevy::prelude::*;
+
+/// The stack of [`TextBox`]es that respond to the [`TextboxInput`] resource.
+///
+/// Only the textbox on top of the stack advances sections and selects choices. Textboxes are
+/// pushed when they are spawned and removed when they are despawned, so a nested textbox
+/// takes focus while it is open and hands it back when it closes. Every textbox responds
+/// while the stack is empty.
+///
+/// A textbox with its own [`TextboxInput`] component, or a [`TextboxPlayer`], is never pushed
+/// and responds only to its own bindings, so that two sequences can await input side by
+/// side.
+///
+/// ```ignore
+/// let keys = InputBindings::default().with_key(KeyCode::KeyE);
+/// commands.spawn((
+///     TextBox::new(left_bundle),
+///     TextboxInput {
+///         advance: keys.clone(),
+///         finish: keys,
+///         ..default()
+///     },
+/// ));
+///
+/// fn focus_shop(mut focus: ResMut<TextboxFocus>, shop: Single<Entity, With<Shop>>) {
+///     focus.focus(*shop);
+/// }
+/// ```
+#[derive(Resource, Debug, Default, Clone)]
+pub struct TextboxFocus {
+    stack: Vec<Entity>,
+}
+
+impl TextboxFocus {
+    /// The textbox on top of the stack.
+    pub fn focused(&self) -> Option<Entity> {
+        self.stack.last().copied()
+    }
+
+    /// Focuses `textbox`, moving it to the top of the stack.
+    pub fn focus(&mut self, textbox: Entity) {
+        self.remove(textbox);
+        self.stack.push(textbox);
+    }
+
+    /// Removes `textbox` from the stack, focusing the textbox beneath it if it was focused.
+    pub fn remove(&mut self, textbox: Entity) {
+        self.stack.retain(|entity| *entity != textbox);
+    }
+
+    pub fn clear(&mut self) {
+        self.stack.clear();
+    }
+
+    /// Iterates over the stack, from the bottom to the focused textbox.
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.stack.iter().copied()
+    }
+
+    /// The bindings that `textbox` responds to, if any.
+    pub(crate) fn input_for<'a>(
+        &self,
+        textbox: Entity,
+        own: Option<&'a TextboxInput>,
+        shared: &'a TextboxInput,
+    ) -> Option<&'a TextboxInput> {
+        own.or_else(|| {
+            self.focused()
+                .is_none_or(|focused| focused == textbox)
+                .then_some(shared)
+        })
+    }
+}
+
+pub(crate) fn track_focus(
+    mut focus: ResMut<TextboxFocus>,
+    added: Query<
+        Entity,
+        (
+            Added<TextBox>,
+            Without<TextboxInput>,
+            Without<TextboxPlayer>,
+        ),
+    >,
+    mut removed: RemovedComponents<TextBox>,
+) {
+    for textbox in removed.read() {
+        focus.remove(textbox);
+    }
+    for textbox in added.iter() {
+        focus.focus(textbox);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FinishTextbox, InputBindings, input::handle_textbox_input};
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy_pretty_text::prelude::*;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<TextboxInput>();
+        world.init_resource::<TextboxFocus>();
+        world.init_resource::<ButtonInput<KeyCode>>();
+        world.init_resource::<Events<FinishTextbox>>();
+        world
+    }
+
+    fn focused(world: &World) -> Option<Entity> {
+        world.resource::<TextboxFocus>().focused()
+    }
+
+    #[test]
+    fn nested_textbox_takes_and_returns_focus() {
+        let mut world = world();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(track_focus);
+
+        let outer = world.spawn(TextBox::new(())).id();
+        schedule.run(&mut world);
+        assert_eq!(focused(&world), Some(outer));
+
+        let nested = world.spawn(TextBox::new(())).id();
+        schedule.run(&mut world);
+        assert_eq!(focused(&world), Some(nested));
+
+        world.despawn(nested);
+        schedule.run(&mut world);
+        assert_eq!(focused(&world), Some(outer));
+    }
+
+    #[test]
+    fn textboxes_with_their_own_input_advance_independently() {
+        let mut world = world();
+        let input = |key| {
+            let keys = InputBindings::default().with_key(key);
+            TextboxInput {
+                advance: keys.clone(),
+                finish: keys,
+                ..default()
+            }
+        };
+        let on_clear = world.register_system(|| {});
+        let mut spawn = |key| {
+            let textbox = world.spawn((TextBox::new(()), input(key))).id();
+            world
+                .spawn((ChildOf(textbox), AwaitClear, OnClear(on_clear)))
+                .id()
+        };
+        let left = spawn(KeyCode::KeyE);
+        let right = spawn(KeyCode::KeyI);
+        world.run_system_once(track_focus).unwrap();
+        assert_eq!(focused(&world), None);
+
+        world
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyE);
+        world.run_system_once(handle_textbox_input).unwrap();
+        assert!(!world.entity(left).contains::<AwaitClear>());
+        assert!(world.entity(right).contains::<AwaitClear>());
+
+        world
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyI);
+        world.run_system_once(handle_textbox_input).unwrap();
+        assert!(!world.entity(right).contains::<AwaitClear>());
+    }
+}
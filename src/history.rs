@@ -0,0 +1,205 @@
+use crate::{ChoiceSelected, SectionFrag, Speaker, TextboxInput, input::Buttons};
+use bevy::prelude::*;
+use bevy_pretty_text::prelude::*;
+use std::{borrow::Cow, collections::VecDeque, time::Duration};
+
+/// Every completed section and selected choice, oldest first.
+///
+/// Once `cap` entries are recorded, the oldest entries are dropped.
+#[derive(Resource, Debug, Clone)]
+pub struct DialogueHistory {
+    entries: VecDeque<HistoryEntry>,
+    cap: usize,
+}
+
+impl Default for DialogueHistory {
+    fn default() -> Self {
+        Self::new(200)
+    }
+}
+
+impl DialogueHistory {
+    pub fn new(cap: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(cap),
+            cap,
+        }
+    }
+
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if self.cap == 0 {
+            return;
+        }
+        if self.entries.len() == self.cap {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &HistoryEntry> + ExactSizeIterator {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub textbox: Entity,
+    /// [`Time::elapsed`] when the entry was recorded.
+    pub time: Duration,
+    pub kind: HistoryKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum HistoryKind {
+    Section {
+        speaker: Option<Speaker>,
+        text: String,
+        section: TypeWriterSection,
+    },
+    Choice {
+        index: usize,
+        text: Cow<'static, str>,
+    },
+}
+
+impl HistoryEntry {
+    pub(crate) fn section(frag: &SectionFrag, time: Duration) -> Self {
+        Self {
+            textbox: frag.textbox,
+            time,
+            kind: HistoryKind::Section {
+                speaker: frag.speaker.clone(),
+                text: frag.section.text.to_string(),
+                section: frag.section.clone(),
+            },
+        }
+    }
+}
+
+pub(crate) fn record_choices(
+    mut reader: EventReader<ChoiceSelected>,
+    mut history: ResMut<DialogueHistory>,
+    time: Res<Time>,
+) {
+    for event in reader.read() {
+        history.push(HistoryEntry {
+            textbox: event.textbox,
+            time: time.elapsed(),
+            kind: HistoryKind::Choice {
+                index: event.index,
+                text: event.text.clone(),
+            },
+        });
+    }
+}
+
+/// A built-in view of the [`DialogueHistory`].
+///
+/// Spawn with a [`Text2d`], or a [`Text`] node. The panel is toggled with
+/// [`TextboxInput::backlog`] and scrolled with `up` and `down`. Textbox input is ignored while
+/// it is open.
+#[derive(Component, Debug, Clone)]
+#[require(Visibility = Visibility::Hidden)]
+pub struct BacklogPanel {
+    /// Number of entries shown at once.
+    pub lines: usize,
+    open: bool,
+    /// Entries scrolled back from the most recent.
+    scroll: usize,
+}
+
+impl BacklogPanel {
+    pub fn new(lines: usize) -> Self {
+        Self {
+            lines,
+            open: false,
+            scroll: 0,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+}
+
+pub(crate) fn backlog_open(panels: Query<&BacklogPanel>) -> bool {
+    panels.iter().any(|panel| panel.open)
+}
+
+pub(crate) fn update_backlog_panels(
+    input: Res<TextboxInput>,
+    buttons: Buttons,
+    history: Res<DialogueHistory>,
+    mut panels: Query<(
+        &mut BacklogPanel,
+        Option<&mut Text2d>,
+        Option<&mut Text>,
+        &mut Visibility,
+    )>,
+) {
+    let toggle = buttons.just_pressed(&input.backlog);
+    let up = buttons.just_pressed(&input.up);
+    let down = buttons.just_pressed(&input.down);
+
+    for (mut panel, text2d, text, mut visibility) in panels.iter_mut() {
+        if toggle {
+            panel.open = !panel.open;
+            panel.scroll = 0;
+            *visibility = if panel.open {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+        }
+
+        if !panel.open {
+            continue;
+        }
+
+        let max_scroll = history.len().saturating_sub(panel.lines);
+        if up && panel.scroll < max_scroll {
+            panel.scroll += 1;
+        }
+        if down && panel.scroll > 0 {
+            panel.scroll -= 1;
+        }
+
+        if panel.is_changed() || history.is_changed() {
+            let end = history.len() - panel.scroll.min(history.len());
+            let start = end.saturating_sub(panel.lines);
+            let backlog = history
+                .iter()
+                .skip(start)
+                .take(end - start)
+                .map(|entry| match &entry.kind {
+                    HistoryKind::Section {
+                        speaker: Some(speaker),
+                        text,
+                        ..
+                    } => format!("{}: {text}", speaker.0),
+                    HistoryKind::Section { text, .. } => text.clone(),
+                    HistoryKind::Choice { text, .. } => format!("> {text}"),
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            if let Some(mut text2d) = text2d {
+                text2d.0 = backlog;
+            } else if let Some(mut text) = text {
+                text.0 = backlog;
+            }
+        }
+    }
+}
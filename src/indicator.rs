@@ -0,0 +1,105 @@
+use crate::ui::offset_val;
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+/// Restricts a [`Continue`](crate::Continue) indicator to one state of the conversation.
+///
+/// A textbox can have one indicator for each kind, e.g. a bobbing arrow while more text
+/// follows and a blinking square on the last section. Indicators without a kind are shown
+/// in both cases.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component, Debug, Default, PartialEq)]
+pub enum ContinueKind {
+    /// More sections follow in the sequence.
+    #[default]
+    More,
+    /// The sequence has no more sections.
+    End,
+}
+
+/// Animates a [`Continue`](crate::Continue) indicator, restarting whenever it is shown.
+#[derive(Component, Debug, Clone, Copy)]
+#[require(ContinueAnimationState)]
+pub enum ContinueAnimation {
+    /// Moves up and down by `amplitude`, `speed` times per second.
+    ///
+    /// A [`Node`] indicator moves through its `top` offset, which UI layout leaves alone.
+    Bob { amplitude: f32, speed: f32 },
+    /// Alternates between shown and transparent every `period` seconds.
+    Blink { period: f32 },
+    /// Steps through `len` texture atlas frames from `first`.
+    Frames { first: usize, len: usize, fps: f32 },
+}
+
+#[derive(Component, Default)]
+pub(crate) struct ContinueAnimationState {
+    elapsed: f32,
+    origin: Option<Vec3>,
+    top: Option<Val>,
+    alpha: Option<f32>,
+}
+
+impl ContinueAnimationState {
+    pub fn restart(&mut self) {
+        self.elapsed = 0.;
+    }
+}
+
+pub(crate) fn animate_continue(
+    time: Res<Time>,
+    mut indicators: Query<(
+        &ContinueAnimation,
+        &mut ContinueAnimationState,
+        &Visibility,
+        &mut Transform,
+        Option<&mut Node>,
+        Option<&mut Sprite>,
+        Option<&mut ImageNode>,
+    )>,
+) {
+    for (animation, mut state, visibility, mut transform, node, sprite, image_node) in
+        indicators.iter_mut()
+    {
+        if *visibility == Visibility::Hidden {
+            continue;
+        }
+
+        state.elapsed += time.delta_secs();
+        let elapsed = state.elapsed;
+        match *animation {
+            ContinueAnimation::Bob { amplitude, speed } => {
+                let offset = (elapsed * speed * TAU).sin() * amplitude;
+                match node {
+                    // UI y points down.
+                    Some(mut node) => {
+                        let origin = *state.top.get_or_insert(node.top);
+                        node.top = offset_val(origin, -offset);
+                    }
+                    None => {
+                        let origin = *state.origin.get_or_insert(transform.translation);
+                        transform.translation.y = origin.y + offset;
+                    }
+                }
+            }
+            ContinueAnimation::Blink { period } => {
+                let shown = period <= 0. || (elapsed / period) as usize % 2 == 0;
+                let color = sprite
+                    .map(|sprite| sprite.map_unchanged(|sprite| &mut sprite.color))
+                    .or(image_node.map(|node| node.map_unchanged(|node| &mut node.color)));
+                if let Some(mut color) = color {
+                    let alpha = *state.alpha.get_or_insert(color.alpha());
+                    color.set_alpha(if shown { alpha } else { 0. });
+                }
+            }
+            ContinueAnimation::Frames { first, len, fps } => {
+                let frame = first + (elapsed * fps) as usize % len.max(1);
+                let atlas = sprite
+                    .and_then(|sprite| sprite.into_inner().texture_atlas.as_mut())
+                    .or(image_node.and_then(|node| node.into_inner().texture_atlas.as_mut()));
+                if let Some(atlas) = atlas {
+                    atlas.index = frame;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,473 @@
+use crate::{
+    AssetFragments, AssetLoad, ChoiceFrag, ChoiceOption, MarkupEffects, SectionFrag, Speaker,
+    TextBoxEntity, Value, is_last_fragment, lifecycle, parse_markup, skip_fragment, spawn_line,
+};
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    platform::collections::HashMap,
+    prelude::*,
+};
+use bevy_pretty_text::prelude::*;
+use bevy_sequence::{fragment::DataLeaf, prelude::*};
+use bladeink::{
+    story::{Story, external_functions::ExternalFunction},
+    value_type::ValueType,
+};
+use std::{cell::RefCell, fmt, rc::Rc, sync::Arc};
+
+/// A compiled Ink story, loaded from the `.ink.json` produced by `inklecate`.
+#[derive(Asset, TypePath, Debug)]
+pub struct InkStory {
+    json: Arc<str>,
+}
+
+/// Runs an [`InkStory`] in the [`TextBox`](crate::TextBox) provided by the [`TextBoxEntity`]
+/// context.
+///
+/// Each line is shown as a section, and Ink choices are presented as a [`ChoiceFrag`]. Lines
+/// may contain the markup supported by [`parse_markup`]. The tags `# speaker: Name` and
+/// `# emotion: name` set the section's [`Speaker`] and portrait, and every tag is sent with
+/// the line as an [`InkLine`]. A story that is still loading runs once it loads.
+///
+/// ```ignore
+/// let frag = InkFrag::new(asset_server.load("tavern.ink.json")).with_path("bartender");
+/// spawn_root_with_context(frag, TextBoxEntity::new(textbox), &mut commands);
+/// ```
+#[derive(Clone)]
+pub struct InkFrag {
+    textbox: Entity,
+    source: InkSource,
+    branch: bool,
+}
+
+#[derive(Clone)]
+enum InkSource {
+    Story {
+        story: Handle<InkStory>,
+        path: Option<String>,
+    },
+    /// Continues the running story after the choice at this index is selected.
+    Choice(usize),
+}
+
+impl InkFrag {
+    pub fn new(story: Handle<InkStory>) -> Self {
+        Self {
+            textbox: Entity::PLACEHOLDER,
+            source: InkSource::Story { story, path: None },
+            branch: false,
+        }
+    }
+
+    /// Starts the story at the knot or stitch `path`, such as `"tavern.bartender"`.
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        if let InkSource::Story { path: start, .. } = &mut self.source {
+            *start = Some(path.into());
+        }
+        self
+    }
+}
+
+crate::impl_textbox_frag!(InkFrag);
+
+/// A line of an [`InkStory`] shown in a [`TextBox`](crate::TextBox), with its tags.
+///
+/// Sent as an [`Event`] and triggered for observers of the textbox when the line's section
+/// is spawned.
+#[derive(Event, Debug, Clone)]
+pub struct InkLine {
+    pub textbox: Entity,
+    pub text: String,
+    pub tags: Vec<String>,
+}
+
+/// The Ink stories running in each [`TextBox`](crate::TextBox), and the external functions
+/// bound to them.
+///
+/// Ink's runtime is not thread safe, so this is a non-send resource.
+///
+/// ```ignore
+/// fn setup(mut stories: NonSendMut<InkStories>) {
+///     stories.bind_external_function("roll", |args: &[Value]| match args {
+///         [Value::Number(sides)] => Some(Value::Number((rand::random::<f64>() * sides).ceil())),
+///         _ => None,
+///     });
+/// }
+///
+/// fn sync_gold(stories: NonSend<InkStories>, textbox: Single<Entity, With<TextBox>>) {
+///     if let Some(Value::Number(gold)) = stories.variable(*textbox, "gold") {
+///         info!("the player has {gold} gold");
+///     }
+/// }
+/// ```
+#[derive(Default)]
+pub struct InkStories {
+    stories: HashMap<Entity, Story>,
+    functions: Vec<(String, Rc<RefCell<InkFunction>>)>,
+}
+
+struct InkFunction(Box<dyn FnMut(&[Value]) -> Option<Value>>);
+
+impl ExternalFunction for InkFunction {
+    fn call(&mut self, name: &str, args: Vec<ValueType>) -> Option<ValueType> {
+        let args = args
+            .iter()
+            .map(|arg| {
+                from_ink(arg).unwrap_or_else(|| {
+                    warn!("unsupported argument passed to ink function `{name}`");
+                    Value::Text(Default::default())
+                })
+            })
+            .collect::<Vec<_>>();
+        (self.0)(&args).map(|value| to_ink(&value))
+    }
+}
+
+impl InkStories {
+    /// Binds the Ink `EXTERNAL` function `name` in stories started after this call.
+    pub fn bind_external_function(
+        &mut self,
+        name: impl Into<String>,
+        function: impl FnMut(&[Value]) -> Option<Value> + 'static,
+    ) {
+        self.functions.push((
+            name.into(),
+            Rc::new(RefCell::new(InkFunction(Box::new(function)))),
+        ));
+    }
+
+    /// The story running in `textbox`.
+    pub fn story(&self, textbox: Entity) -> Option<&Story> {
+        self.stories.get(&textbox)
+    }
+
+    pub fn story_mut(&mut self, textbox: Entity) -> Option<&mut Story> {
+        self.stories.get_mut(&textbox)
+    }
+
+    /// Reads the global variable `name` of the story running in `textbox`.
+    ///
+    /// Lists, divert targets and variable pointers are not converted.
+    pub fn variable(&self, textbox: Entity, name: &str) -> Option<Value> {
+        from_ink(&self.stories.get(&textbox)?.get_variable(name)?)
+    }
+
+    /// Sets the global variable `name` of the story running in `textbox`.
+    pub fn set_variable(
+        &mut self,
+        textbox: Entity,
+        name: &str,
+        value: impl Into<Value>,
+    ) -> Result<(), InkError> {
+        let Some(story) = self.stories.get_mut(&textbox) else {
+            return Err(InkError::NotRunning(textbox));
+        };
+        story
+            .set_variable(name, &to_ink(&value.into()))
+            .map_err(|error| InkError::Story(error.to_string()))
+    }
+
+    fn start(
+        &mut self,
+        textbox: Entity,
+        json: &str,
+        path: Option<&str>,
+    ) -> Result<&mut Story, InkError> {
+        let mut story = Story::new(json).map_err(|error| InkError::Story(error.to_string()))?;
+        for (name, function) in &self.functions {
+            story
+                .bind_external_function(name, function.clone(), true)
+                .map_err(|error| InkError::Story(error.to_string()))?;
+        }
+        if let Some(path) = path {
+            story
+                .choose_path_string(path, true, None)
+                .map_err(|error| InkError::Story(error.to_string()))?;
+        }
+        self.stories.insert(textbox, story);
+        Ok(self.stories.get_mut(&textbox).unwrap())
+    }
+}
+
+fn from_ink(value: &ValueType) -> Option<Value> {
+    Some(match value {
+        ValueType::Bool(value) => Value::Bool(*value),
+        ValueType::Int(value) => Value::Number(*value as f64),
+        ValueType::Float(value) => Value::Number(*value as f64),
+        ValueType::String(value) => Value::Text(value.string.clone().into()),
+        _ => return None,
+    })
+}
+
+fn to_ink(value: &Value) -> ValueType {
+    match value {
+        Value::Bool(value) => ValueType::Bool(*value),
+        Value::Number(value) if value.fract() == 0.0 => ValueType::Int(*value as i32),
+        Value::Number(value) => ValueType::Float(*value as f32),
+        Value::Text(text) => ValueType::new_string(text),
+    }
+}
+
+/// An error running an [`InkStory`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InkError {
+    /// No story is running in this [`TextBox`](crate::TextBox).
+    NotRunning(Entity),
+    /// An error reported by the Ink runtime.
+    Story(String),
+}
+
+impl fmt::Display for InkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotRunning(textbox) => write!(f, "no ink story is running in {textbox}"),
+            Self::Story(error) => write!(f, "ink error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for InkError {}
+
+/// Plays [`InkFrag`]s, holding those whose story is still loading until it loads.
+pub(crate) fn play_ink(
+    mut commands: Commands,
+    mut fragments: AssetFragments<InkFrag, InkStory>,
+    mut stories: NonSendMut<InkStories>,
+    parents: Query<&ChildOf>,
+    children: Query<&Children>,
+) {
+    for event in fragments.read() {
+        let textbox = event.data.textbox;
+        let last = !event.data.branch && is_last_fragment(event.id.entity(), &parents, &children);
+        let started = match &event.data.source {
+            InkSource::Story { story, path } => {
+                let story = match fragments.get(story) {
+                    AssetLoad::Loaded(story) => story,
+                    AssetLoad::Loading => {
+                        fragments.hold(event);
+                        continue;
+                    }
+                    AssetLoad::Failed => {
+                        error!("ink story failed to load");
+                        skip_fragment(&mut commands, textbox, event.id, event.end(), last);
+                        continue;
+                    }
+                };
+                stories
+                    .start(textbox, &story.json, path.as_deref())
+                    .map(|_| ())
+            }
+            InkSource::Choice(index) => match stories.story_mut(textbox) {
+                Some(story) => story
+                    .choose_choice_index(*index)
+                    .map_err(|error| InkError::Story(error.to_string())),
+                None => Err(InkError::NotRunning(textbox)),
+            },
+        };
+
+        let end = StoryEnd {
+            fragment: event.id,
+            end: event.end(),
+            last,
+        };
+        match started {
+            Ok(()) => step(&mut commands, &mut stories, textbox, end),
+            Err(error) => {
+                error!("{error}");
+                end.finish(&mut commands, textbox);
+            }
+        }
+    }
+}
+
+/// Ends an [`InkFrag`] once its story is finished.
+#[derive(Clone, Copy)]
+struct StoryEnd {
+    fragment: FragmentId,
+    end: FragmentEndEvent,
+    /// Whether the [`InkFrag`] is the last fragment of its sequence, until a line finishes
+    /// the sequence.
+    last: bool,
+}
+
+impl StoryEnd {
+    fn finish(self, commands: &mut Commands, textbox: Entity) {
+        if self.last {
+            lifecycle::finish_sequence(commands, textbox, self.fragment);
+        }
+        commands.send_event(self.end);
+    }
+}
+
+/// Shows the next line or choice of the story running in `textbox`, or ends the story once
+/// it is finished.
+///
+/// The story ends the conversation if its [`InkFrag`] is `last` in its sequence.
+fn step(commands: &mut Commands, stories: &mut InkStories, textbox: Entity, end: StoryEnd) {
+    let Some(story) = stories.story_mut(textbox) else {
+        end.finish(commands, textbox);
+        return;
+    };
+
+    while story.can_continue() {
+        let line = match story.cont() {
+            Ok(line) => line.trim().to_owned(),
+            Err(error) => {
+                error!("ink error: {error}");
+                break;
+            }
+        };
+        if line.is_empty() {
+            continue;
+        }
+        let tags = story.get_current_tags().unwrap_or_else(|error| {
+            error!("ink error: {error}");
+            Vec::new()
+        });
+
+        let text = line.replace('{', "{{").replace('}', "}}");
+        let mut frag = SectionFrag::from_world(move |world: &World| {
+            parse_markup(&text, world.resource::<MarkupEffects>()).unwrap_or_else(|error| {
+                error!("{error} in ink line `{text}`");
+                TypeWriterSection::from(text.clone())
+            })
+        });
+        for tag in &tags {
+            match tag.split_once(':') {
+                Some((key, value)) if key.trim() == "speaker" => {
+                    frag.speaker = Some(Speaker::new(value.trim().to_owned()));
+                }
+                Some((key, value)) if key.trim() == "emotion" => {
+                    frag.emotion = Some(value.trim().to_owned().into());
+                }
+                _ => {}
+            }
+        }
+
+        let line = InkLine {
+            textbox,
+            text: line,
+            tags,
+        };
+        commands.send_event(line.clone());
+        commands.trigger_targets(line, textbox);
+
+        let last_line = end.last && !story.can_continue() && story.get_current_choices().is_empty();
+        let end = StoryEnd {
+            last: end.last && !last_line,
+            ..end
+        };
+        spawn_line(
+            commands,
+            textbox,
+            last_line,
+            frag,
+            move |commands: &mut Commands| {
+                commands.run_system_cached_with(continue_story, (textbox, end));
+            },
+        );
+        return;
+    }
+
+    let choices = story.get_current_choices();
+    if choices.is_empty() {
+        stories.stories.remove(&textbox);
+        end.finish(commands, textbox);
+        return;
+    }
+
+    let frag = ChoiceFrag::new(choices.iter().enumerate().map(|(index, choice)| {
+        ChoiceOption::new(choice.text.clone()).then(InkFrag {
+            textbox: Entity::PLACEHOLDER,
+            source: InkSource::Choice(index),
+            branch: false,
+        })
+    }));
+    // The selected branch runs the rest of the story before the choice ends.
+    spawn_line(
+        commands,
+        textbox,
+        end.last,
+        frag,
+        move |commands: &mut Commands| {
+            commands.send_event(end.end);
+        },
+    );
+}
+
+fn continue_story(
+    In((textbox, end)): In<(Entity, StoryEnd)>,
+    mut commands: Commands,
+    mut stories: NonSendMut<InkStories>,
+) {
+    step(&mut commands, &mut stories, textbox, end);
+}
+
+#[derive(Default)]
+pub(crate) struct InkLoader;
+
+#[derive(Debug)]
+pub enum InkLoadError {
+    Io(std::io::Error),
+    Utf8(std::string::FromUtf8Error),
+    /// The file is not a story compiled by `inklecate`.
+    Story {
+        path: String,
+        error: String,
+    },
+}
+
+impl fmt::Display for InkLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "could not read ink story: {error}"),
+            Self::Utf8(error) => write!(f, "ink story is not valid UTF-8: {error}"),
+            Self::Story { path, error } => write!(f, "could not load `{path}`: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for InkLoadError {}
+
+impl From<std::io::Error> for InkLoadError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<std::string::FromUtf8Error> for InkLoadError {
+    fn from(value: std::string::FromUtf8Error) -> Self {
+        Self::Utf8(value)
+    }
+}
+
+impl AssetLoader for InkLoader {
+    type Asset = InkStory;
+    type Settings = ();
+    type Error = InkLoadError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let json = String::from_utf8(bytes)?;
+        // `inklecate` writes a byte order mark.
+        let json = json.trim_start_matches('\u{feff}');
+
+        if let Err(error) = Story::new(json) {
+            return Err(InkLoadError::Story {
+                path: load_context.path().display().to_string(),
+                error: error.to_string(),
+            });
+        }
+        Ok(InkStory { json: json.into() })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ink.json"]
+    }
+}
@@ -0,0 +1,271 @@
+use crate::{FinishTextbox, TextBox, TextboxFocus, TextboxPlayer, clear_section};
+use bevy::{ecs::system::SystemParam, prelude::*, window::PrimaryWindow};
+use bevy_pretty_text::prelude::*;
+
+/// Maps player input to textbox actions.
+///
+/// `advance` clears a section that is awaiting input and `finish` reveals the rest of a
+/// section that is still scrolling. Both are bound to the same buttons by default, so a
+/// single press does whichever applies.
+///
+/// `up` and `down` move through the options of a [`ChoiceFrag`](crate::ChoiceFrag),
+/// and `advance` selects one.
+///
+/// `backlog` toggles any [`BacklogPanel`](crate::BacklogPanel), which is scrolled with
+/// `up` and `down`.
+///
+/// As a resource, the bindings apply to the focused [`TextBox`], see [`TextboxFocus`]. As a
+/// component of a textbox, they apply to that textbox alone. Only the gamepad buttons apply
+/// to a textbox with a [`TextboxPlayer`].
+///
+/// The plugin initializes the resource with the default bindings unless it is already
+/// inserted, and it can be replaced at any time.
+#[derive(Resource, Component, Debug, Clone)]
+pub struct TextboxInput {
+    pub advance: InputBindings,
+    pub finish: InputBindings,
+    pub up: InputBindings,
+    pub down: InputBindings,
+    pub backlog: InputBindings,
+}
+
+impl Default for TextboxInput {
+    fn default() -> Self {
+        let bindings = InputBindings::default()
+            .with_key(KeyCode::Space)
+            .with_key(KeyCode::Enter)
+            .with_mouse_button(MouseButton::Left)
+            .with_gamepad_button(GamepadButton::South);
+
+        Self {
+            advance: bindings.clone(),
+            finish: bindings,
+            up: InputBindings::default()
+                .with_key(KeyCode::ArrowUp)
+                .with_key(KeyCode::KeyW)
+                .with_gamepad_button(GamepadButton::DPadUp),
+            down: InputBindings::default()
+                .with_key(KeyCode::ArrowDown)
+                .with_key(KeyCode::KeyS)
+                .with_gamepad_button(GamepadButton::DPadDown),
+            backlog: InputBindings::default()
+                .with_key(KeyCode::KeyH)
+                .with_gamepad_button(GamepadButton::Select),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct InputBindings {
+    pub keys: Vec<KeyCode>,
+    pub mouse_buttons: Vec<MouseButton>,
+    pub gamepad_buttons: Vec<GamepadButton>,
+}
+
+impl InputBindings {
+    pub fn with_key(mut self, key: KeyCode) -> Self {
+        self.keys.push(key);
+        self
+    }
+
+    pub fn with_mouse_button(mut self, button: MouseButton) -> Self {
+        self.mouse_buttons.push(button);
+        self
+    }
+
+    pub fn with_gamepad_button(mut self, button: GamepadButton) -> Self {
+        self.gamepad_buttons.push(button);
+        self
+    }
+}
+
+/// Button state read by the textbox input systems.
+///
+/// Every source is optional so that headless apps can insert only the
+/// [`ButtonInput`] resources they care about.
+#[derive(SystemParam)]
+pub(crate) struct Buttons<'w, 's> {
+    keys: Option<Res<'w, ButtonInput<KeyCode>>>,
+    mouse: Option<Res<'w, ButtonInput<MouseButton>>>,
+    gamepads: Query<'w, 's, (Entity, &'static Gamepad)>,
+}
+
+impl Buttons<'_, '_> {
+    pub fn just_pressed(&self, bindings: &InputBindings) -> bool {
+        self.keys
+            .as_ref()
+            .is_some_and(|keys| keys.any_just_pressed(bindings.keys.iter().copied()))
+            || self.mouse.as_ref().is_some_and(|mouse| {
+                mouse.any_just_pressed(bindings.mouse_buttons.iter().copied())
+            })
+            || self
+                .gamepads
+                .iter()
+                .any(|(_, gamepad)| gamepad_just_pressed(gamepad, bindings))
+    }
+
+    /// Reads only the gamepad buttons of `bindings`, on `gamepad`.
+    pub fn gamepad_just_pressed(&self, gamepad: Entity, bindings: &InputBindings) -> bool {
+        self.gamepads
+            .get(gamepad)
+            .is_ok_and(|(_, gamepad)| gamepad_just_pressed(gamepad, bindings))
+    }
+}
+
+fn gamepad_just_pressed(gamepad: &Gamepad, bindings: &InputBindings) -> bool {
+    bindings
+        .gamepad_buttons
+        .iter()
+        .any(|button| gamepad.just_pressed(*button))
+}
+
+/// Button state of the input that each [`TextBox`] responds to.
+#[derive(SystemParam)]
+pub(crate) struct TextboxButtons<'w, 's> {
+    input: Res<'w, TextboxInput>,
+    focus: Res<'w, TextboxFocus>,
+    buttons: Buttons<'w, 's>,
+    textboxes: Query<
+        'w,
+        's,
+        (
+            Option<&'static TextboxInput>,
+            Option<&'static TextboxPlayer>,
+        ),
+        With<TextBox>,
+    >,
+}
+
+impl TextboxButtons<'_, '_> {
+    /// Whether the bindings selected by `bindings` were just pressed for `textbox`.
+    pub fn just_pressed(
+        &self,
+        textbox: Entity,
+        bindings: impl Fn(&TextboxInput) -> &InputBindings,
+    ) -> bool {
+        let (own, player) = self.textboxes.get(textbox).unwrap_or_default();
+        match player {
+            Some(player) => self
+                .buttons
+                .gamepad_just_pressed(player.gamepad, bindings(own.unwrap_or(&self.input))),
+            None => self
+                .focus
+                .input_for(textbox, own, &self.input)
+                .is_some_and(|input| self.buttons.just_pressed(bindings(input))),
+        }
+    }
+}
+
+/// The cursor position in world space, as seen by the first active 2D camera.
+#[derive(SystemParam)]
+pub(crate) struct CursorPosition<'w, 's> {
+    windows: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    cameras: Query<'w, 's, (&'static Camera, &'static GlobalTransform), With<Camera2d>>,
+}
+
+impl CursorPosition<'_, '_> {
+    pub fn world_position(&self) -> Option<Vec2> {
+        let cursor = self.windows.single().ok()?.cursor_position()?;
+        let (camera, transform) = self.cameras.iter().find(|(camera, _)| camera.is_active)?;
+        camera.viewport_to_world_2d(transform, cursor).ok()
+    }
+}
+
+pub(crate) fn handle_textbox_input(
+    mut commands: Commands,
+    buttons: TextboxButtons,
+    mut writer: EventWriter<FinishTextbox>,
+    textboxes: Query<(Entity, &Children), With<TextBox>>,
+    sections: Query<(Option<&OnClear>, Has<Scroll>, Has<AwaitClear>)>,
+) {
+    for (textbox, children) in textboxes.iter() {
+        let advance = buttons.just_pressed(textbox, |input| &input.advance);
+        let finish = buttons.just_pressed(textbox, |input| &input.finish);
+        if !advance && !finish {
+            continue;
+        }
+
+        for child in children.iter() {
+            let Ok((on_clear, scrolling, awaiting)) = sections.get(child) else {
+                continue;
+            };
+
+            match (awaiting, on_clear, scrolling) {
+                (true, Some(on_clear), _) if advance => {
+                    clear_section(&mut commands, child, on_clear);
+                }
+                (false, _, true) if finish => {
+                    writer.write(FinishTextbox(textbox));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<TextboxInput>();
+        world.init_resource::<TextboxFocus>();
+        world.init_resource::<ButtonInput<KeyCode>>();
+        world.init_resource::<Events<FinishTextbox>>();
+        world
+    }
+
+    /// Spawns a section in `textbox` that has scrolled to the end and awaits input.
+    fn spawn_awaiting(world: &mut World, textbox: Entity) -> Entity {
+        let on_clear = world.register_system(|| {});
+        world
+            .spawn((ChildOf(textbox), AwaitClear, OnClear(on_clear)))
+            .id()
+    }
+
+    fn press(world: &mut World, key: KeyCode) {
+        world.resource_mut::<ButtonInput<KeyCode>>().press(key);
+        world.run_system_once(handle_textbox_input).unwrap();
+    }
+
+    #[test]
+    fn advance_clears_an_awaiting_section() {
+        let mut world = world();
+        let textbox = world.spawn(TextBox::new(())).id();
+        let section = spawn_awaiting(&mut world, textbox);
+
+        press(&mut world, KeyCode::Space);
+
+        assert!(!world.entity(section).contains::<AwaitClear>());
+        assert!(world.resource::<Events<FinishTextbox>>().is_empty());
+    }
+
+    #[test]
+    fn finish_reveals_a_scrolling_section() {
+        let mut world = world();
+        let textbox = world.spawn(TextBox::new(())).id();
+        world.spawn((ChildOf(textbox), Scroll::default()));
+
+        press(&mut world, KeyCode::Enter);
+
+        let finished = world
+            .resource::<Events<FinishTextbox>>()
+            .iter_current_update_events()
+            .map(|finish| finish.0)
+            .collect::<Vec<_>>();
+        assert_eq!(finished, [textbox]);
+    }
+
+    #[test]
+    fn unbound_keys_are_ignored() {
+        let mut world = world();
+        let textbox = world.spawn(TextBox::new(())).id();
+        let section = spawn_awaiting(&mut world, textbox);
+
+        press(&mut world, KeyCode::KeyQ);
+
+        assert!(world.entity(section).contains::<AwaitClear>());
+    }
+}
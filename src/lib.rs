@@ -1,13 +1,13 @@
 //! Integrates `bevy_pretty_text` and `bevy_sequence` into a simple `TextBox`.
 //!
 //! Here is a simple example:
-//! ```
+//! ```ignore
 //! fn startup(mut commands: Commands, asset_server: Res<AssetServer>) {
 //!     commands.spawn(Camera2d);
 //!
 //!     let entity = commands
 //!         .spawn((
-//!             TextBox,
+//!             TextBox::new(TextFont::from_font_size(24.)),
 //!             Sprite {
 //!                 image: asset_server.load("textbox.png"),
 //!                 anchor: Anchor::TopLeft,
@@ -30,15 +30,88 @@
 //!     let frag = (s!("`Hello|green`[0.5], `World`[wave]!"), "My name is Nic.")
 //!         .always()
 //!         .once()
-//!         .on_end(move |mut commands: Commands| commands.entity(entity).despawn_recursive());
-//!     spawn_root_with_context(frag, TextBoxEntity(entity), &mut commands);
+//!         .on_end(move |mut writer: EventWriter<CloseTextbox>| {
+//!             writer.write(CloseTextbox(entity));
+//!         });
+//!     spawn_root_with_context(frag, TextBoxEntity::new(entity), &mut commands);
 //! }
 //! ```
 
-use bevy::prelude::*;
+use bevy::{
+    asset::LoadState,
+    ecs::{
+        component::HookContext,
+        system::{SystemId, SystemParam},
+        world::DeferredWorld,
+    },
+    prelude::*,
+    render::view::VisibilitySystems,
+};
 use bevy_pretty_text::prelude::*;
 use bevy_sequence::{fragment::DataLeaf, prelude::*};
-use std::sync::Arc;
+use std::{borrow::Cow, collections::VecDeque, sync::Arc};
+
+pub use auto_advance::AutoAdvance;
+pub use blip::{Blip, SpeakerBlips};
+pub use bubble::SpeechBubble;
+pub use builder::SectionBuilder;
+pub use choice::{ChoiceColors, ChoiceCursor, ChoiceFrag, ChoiceOption, ChoiceSelected};
+pub use command::{CommandArg, CommandArgs, CommandError, InlineCommands, SkippedCommands};
+pub use focus::TextboxFocus;
+pub use history::{BacklogPanel, DialogueHistory, HistoryEntry, HistoryKind};
+pub use indicator::{ContinueAnimation, ContinueKind};
+#[cfg(feature = "ink")]
+pub use ink::{InkError, InkFrag, InkLine, InkLoadError, InkStories, InkStory};
+pub use input::{InputBindings, TextboxInput};
+pub use lifecycle::{
+    SectionAwaitingInput, SectionCleared, SectionScrolled, SectionStarted, SequenceFinished,
+};
+#[cfg(feature = "fluent")]
+pub use localization::{FluentAsset, FluentLoadError, Localization};
+pub use markup::{MarkupEffects, MarkupError, MarkupErrorKind, parse_markup};
+pub use paginate::TextArea;
+pub use player::TextboxPlayer;
+pub use portrait::{Portrait, PortraitImage, Portraits};
+pub use script::{DialogueScript, ScriptError, ScriptErrorKind, ScriptFrag};
+pub use source_error::{SourceError, SourceErrorKind};
+pub use speaker::{Nameplate, Speaker, UpdateNameplate};
+pub use transition::{CloseTextbox, TextBoxTransition, Transition, TransitionEffect};
+pub use ui::UiReferenceResolution;
+pub use variables::{Value, VariableError, VariableSource, Variables};
+pub use yarn::{YarnCommand, YarnError, YarnErrorKind, YarnFrag, YarnProject};
+
+use auto_advance::AutoAdvanceTimer;
+use blip::BlipState;
+use command::{CommandMarker, PendingCommands};
+use indicator::ContinueAnimationState;
+use transition::{HeldScroll, TransitionState};
+
+mod auto_advance;
+mod blip;
+mod bubble;
+mod builder;
+mod choice;
+mod command;
+mod focus;
+mod history;
+mod indicator;
+#[cfg(feature = "ink")]
+mod ink;
+mod input;
+mod lifecycle;
+#[cfg(feature = "fluent")]
+mod localization;
+mod markup;
+mod paginate;
+mod player;
+mod portrait;
+mod script;
+mod source_error;
+mod speaker;
+mod transition;
+mod ui;
+mod variables;
+mod yarn;
 
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 struct TextboxSystems;
@@ -48,11 +121,122 @@ pub struct TextboxPlugin;
 impl Plugin for TextboxPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((PrettyTextPlugin, SequencePlugin))
+            .init_resource::<TextboxInput>()
+            .init_resource::<InlineCommands>()
+            .init_resource::<AutoAdvance>()
+            .init_resource::<TextboxFocus>()
+            .init_resource::<Portraits>()
+            .init_resource::<ChoiceColors>()
+            .init_resource::<SpeakerBlips>()
+            .init_resource::<DialogueHistory>()
+            .init_resource::<Variables>()
+            .init_resource::<MarkupEffects>()
+            .register_type::<TextBoxEntity>()
+            .register_type::<SectionFrag>()
+            .register_type::<UpdateContinueVis>()
+            .register_type::<ContinueKind>()
+            .register_type::<Speaker>()
+            .register_type::<UpdateNameplate>()
             .add_event::<UpdateContinueVis>()
+            .add_event::<FinishTextbox>()
+            .add_event::<UpdateNameplate>()
+            .add_event::<CloseTextbox>()
+            .add_event::<ChoiceSelected>()
+            .add_event::<SectionStarted>()
+            .add_event::<SectionScrolled>()
+            .add_event::<SectionAwaitingInput>()
+            .add_event::<SectionCleared>()
+            .add_event::<SequenceFinished>()
             .add_event::<FragmentEvent<SectionFrag>>()
+            .add_event::<FragmentEvent<ChoiceFrag>>()
+            .add_event::<FragmentEvent<ScriptFrag>>()
+            .add_event::<FragmentEvent<YarnFrag>>()
+            .add_event::<YarnCommand>()
+            .init_asset::<DialogueScript>()
+            .init_asset_loader::<script::DialogueScriptLoader>()
+            .init_asset::<YarnProject>()
+            .init_asset_loader::<yarn::YarnLoader>()
+            .add_systems(
+                Update,
+                (
+                    transition::start_transitions,
+                    transition::close_textboxes,
+                    transition::animate_transitions,
+                    history::update_backlog_panels,
+                    focus::track_focus,
+                    input::handle_textbox_input.run_if(not(history::backlog_open)),
+                    auto_advance::auto_advance_sections,
+                    finish_textboxes,
+                    blip::play_blips,
+                    command::run_inline_commands,
+                    spawn_section_frags,
+                    update_continue_visibility,
+                    indicator::animate_continue,
+                    speaker::update_nameplates,
+                    portrait::update_portraits,
+                )
+                    .chain()
+                    .in_set(TextboxSystems),
+            )
             .add_systems(
                 Update,
-                (spawn_section_frags, update_continue_visibility).in_set(TextboxSystems),
+                (
+                    script::play_scripts,
+                    yarn::play_yarn,
+                    choice::select_choices.run_if(not(history::backlog_open)),
+                    choice::spawn_choice_frags,
+                    choice::offset_choice_entries,
+                    choice::navigate_choices.run_if(not(history::backlog_open)),
+                    choice::highlight_choices,
+                    choice::hide_choice_cursors,
+                    history::record_choices,
+                )
+                    .chain()
+                    .in_set(TextboxSystems),
+            )
+            .add_systems(
+                Update,
+                ui::scale_ui.run_if(resource_exists::<UiReferenceResolution>),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    player::show_player_textboxes
+                        .before(UiSystem::Layout)
+                        .before(VisibilitySystems::CheckVisibility),
+                    bubble::position_speech_bubbles.before(UiSystem::Layout),
+                ),
+            );
+
+        #[cfg(feature = "fluent")]
+        app.init_asset::<FluentAsset>()
+            .init_asset_loader::<localization::FluentLoader>()
+            .add_systems(
+                Update,
+                (
+                    localization::update_bundles
+                        .run_if(resource_exists::<Localization>)
+                        .before(TextboxSystems),
+                    localization::relocalize_sections
+                        .run_if(resource_exists_and_changed::<Localization>)
+                        .in_set(TextboxSystems),
+                    localization::relocalize_choices
+                        .run_if(resource_exists_and_changed::<Localization>)
+                        .in_set(TextboxSystems),
+                ),
+            );
+
+        #[cfg(feature = "ink")]
+        app.init_non_send_resource::<InkStories>()
+            .add_event::<FragmentEvent<InkFrag>>()
+            .add_event::<InkLine>()
+            .init_asset::<InkStory>()
+            .init_asset_loader::<ink::InkLoader>()
+            .add_systems(
+                Update,
+                ink::play_ink
+                    .before(choice::spawn_choice_frags)
+                    .in_set(TextboxSystems),
             );
     }
 }
@@ -60,14 +244,60 @@ impl Plugin for TextboxPlugin {
 #[derive(Component)]
 pub struct TextBox {
     bundle: TextBoxBundle,
+    mode: TextBoxMode,
+    choice_spacing: f32,
+    text_area: Option<TextArea>,
+}
+
+/// How a [`TextBox`] and its children are laid out.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TextBoxMode {
+    /// World space [`Sprite`]s and [`Text2d`], positioned with [`Transform`]s.
+    #[default]
+    Sprite,
+    /// [`Node`]s laid out by `bevy_ui`.
+    ///
+    /// Sections and choices are spawned as child nodes, so the [`TextBox`] node's flex
+    /// settings and padding apply to them. [`Nameplate`] takes a [`Text`] and [`Portrait`]
+    /// an [`ImageNode`].
+    Ui,
 }
 
 impl TextBox {
     pub fn new(bundle: impl Bundle + Clone) -> Self {
         Self {
             bundle: TextBoxBundle::new(bundle),
+            mode: TextBoxMode::Sprite,
+            choice_spacing: 30.,
+            text_area: None,
         }
     }
+
+    /// Creates a [`TextBoxMode::Ui`] textbox.
+    ///
+    /// `bundle` is inserted on each section node, alongside a default [`Node`].
+    pub fn ui(bundle: impl Bundle + Clone) -> Self {
+        Self {
+            mode: TextBoxMode::Ui,
+            ..Self::new(bundle)
+        }
+    }
+
+    pub fn mode(&self) -> TextBoxMode {
+        self.mode
+    }
+
+    /// Splits sections that overflow `area` into pages.
+    pub fn with_text_area(mut self, area: TextArea) -> Self {
+        self.text_area = Some(area);
+        self
+    }
+
+    /// Sets the vertical distance between the options of a [`ChoiceFrag`].
+    pub fn with_choice_spacing(mut self, spacing: f32) -> Self {
+        self.choice_spacing = spacing;
+        self
+    }
 }
 
 #[derive(Clone)]
@@ -81,104 +311,701 @@ impl TextBoxBundle {
     }
 }
 
-#[derive(Component)]
-pub struct TextBoxEntity(Entity);
+/// Context that provides the [`TextBox`] of the [`SectionFrag`]s and [`ChoiceFrag`]s in a
+/// sequence.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+#[reflect(Component, Debug)]
+pub struct TextBoxEntity {
+    entity: Entity,
+    /// Whether this is the context of a [`ChoiceOption`] branch, which is followed by the
+    /// rest of the sequence that presented the choice.
+    branch: bool,
+}
 
 impl TextBoxEntity {
     pub fn new(entity: Entity) -> Self {
-        Self(entity)
+        Self {
+            entity,
+            branch: false,
+        }
+    }
+
+    /// The context of fragments played in place of a fragment, such as a [`ChoiceOption`]
+    /// branch, which end the sequence only if that fragment was `last` in its own.
+    pub(crate) fn nested(entity: Entity, last: bool) -> Self {
+        Self {
+            entity,
+            branch: !last,
+        }
+    }
+
+    pub fn entity(&self) -> Entity {
+        self.entity
     }
 }
 
 #[derive(Component)]
 pub struct Continue;
 
-#[derive(Event)]
+#[derive(Event, Debug, Clone, Copy, Reflect)]
+#[reflect(Debug)]
 pub struct UpdateContinueVis {
     entity: Entity,
     visibility: Visibility,
+    kind: ContinueKind,
 }
 
 impl UpdateContinueVis {
     pub fn new(entity: Entity, visibility: Visibility) -> Self {
-        Self { entity, visibility }
+        Self {
+            entity,
+            visibility,
+            kind: ContinueKind::More,
+        }
+    }
+
+    pub fn with_kind(mut self, kind: ContinueKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// The [`TextBox`] whose [`Continue`] indicators are updated.
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    pub fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    pub fn kind(&self) -> ContinueKind {
+        self.kind
     }
 }
 
 fn update_continue_visibility(
     textbox_query: Query<&Children, With<TextBox>>,
-    mut continue_query: Query<&mut Visibility, With<Continue>>,
+    mut continue_query: Query<
+        (
+            &mut Visibility,
+            Option<&ContinueKind>,
+            Option<&mut ContinueAnimationState>,
+        ),
+        With<Continue>,
+    >,
     mut reader: EventReader<UpdateContinueVis>,
 ) {
     for event in reader.read() {
         if let Ok(children) = textbox_query.get(event.entity) {
             for child in children.iter() {
-                if let Ok(mut cont) = continue_query.get_mut(child) {
-                    *cont = event.visibility;
+                if let Ok((mut cont, kind, animation)) = continue_query.get_mut(child) {
+                    *cont = match kind {
+                        Some(kind) if *kind != event.kind => Visibility::Hidden,
+                        _ => event.visibility,
+                    };
+                    if let Some(mut animation) = animation {
+                        animation.restart();
+                    }
                 }
             }
         }
     }
 }
 
-// TODO: this leaks memory (SystemId)
+/// Whether `leaf` is the last fragment of its sequence.
+///
+/// Fragments form a hierarchy, so this holds when every fragment from `leaf` to the root
+/// is the last child of its parent.
+fn is_last_fragment(leaf: Entity, parents: &Query<&ChildOf>, children: &Query<&Children>) -> bool {
+    let mut fragment = leaf;
+    while let Ok(child_of) = parents.get(fragment) {
+        let parent = child_of.parent();
+        if children
+            .get(parent)
+            .is_ok_and(|siblings| siblings.last() != Some(&fragment))
+        {
+            return false;
+        }
+        fragment = parent;
+    }
+    true
+}
+
+/// Instantly completes the scrolling section of a [`TextBox`].
+///
+/// The section then awaits a clear as if it had finished scrolling on its own.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct FinishTextbox(pub Entity);
+
+fn finish_textboxes(
+    mut commands: Commands,
+    mut reader: EventReader<FinishTextbox>,
+    inline_commands: Res<InlineCommands>,
+    textbox_query: Query<&Children, With<TextBox>>,
+    mut section_query: Query<(&mut Scroll, &OnScrollEnd), Without<AwaitClear>>,
+) {
+    for event in reader.read() {
+        let Ok(children) = textbox_query.get(event.0) else {
+            continue;
+        };
+
+        for child in children.iter() {
+            if let Ok((mut scroll, on_end)) = section_query.get_mut(child) {
+                scroll.finish();
+                commands.run_system(on_end.0);
+                commands.entity(child).remove::<(OnScrollEnd, BlipState)>();
+                if inline_commands.skipped == SkippedCommands::Skip {
+                    commands.entity(child).remove::<PendingCommands>();
+                }
+            }
+        }
+    }
+}
+
+/// Clears a section that is awaiting input.
+pub(crate) fn clear_section(commands: &mut Commands, section: Entity, on_clear: &OnClear) {
+    commands.entity(section).remove::<AwaitClear>();
+    commands.run_system(on_clear.0);
+}
+
+/// One-shot systems registered for a single section.
+///
+/// They are unregistered when the section entity is despawned, either after being
+/// cleared or along with its [`TextBox`].
+#[derive(Component)]
+#[component(on_remove = unregister_section_systems)]
+struct SectionSystems(Vec<SystemId>);
+
+fn unregister_section_systems(mut world: DeferredWorld, ctx: HookContext) {
+    let systems = std::mem::take(&mut world.get_mut::<SectionSystems>(ctx.entity).unwrap().0);
+    let mut commands = world.commands();
+    for system in systems {
+        commands.unregister_system(system);
+    }
+}
+
 fn spawn_section_frags(
+    world: &World,
     mut commands: Commands,
     mut reader: EventReader<FragmentEvent<SectionFrag>>,
-    textboxes: Query<&TextBox>,
+    textboxes: Query<(&TextBox, Option<&TransitionState>)>,
+    parents: Query<&ChildOf>,
+    children: Query<&Children>,
 ) {
     for event in reader.read() {
-        let textbox = event.data.textbox;
-        let end = event.end();
-        let entity = commands.spawn_empty().id();
-        let on_end = commands.register_system(
-            move |mut commands: Commands, mut writer: EventWriter<UpdateContinueVis>| {
-                let id = commands.register_system(
-                    move |mut commands: Commands,
-                          mut frag_writer: EventWriter<FragmentEndEvent>,
-                          mut continue_writer: EventWriter<UpdateContinueVis>| {
-                        frag_writer.write(end);
-                        commands.entity(entity).despawn();
-                        continue_writer.write(UpdateContinueVis::new(textbox, Visibility::Hidden));
-                    },
-                );
-
-                commands.entity(entity).insert((AwaitClear, OnClear(id)));
-                writer.write(UpdateContinueVis::new(textbox, Visibility::Visible));
+        let mut frag = event.data.clone();
+        let (section, inline_commands) = resolve_section(&frag, world);
+        frag.section = section;
+        let last = !frag.branch && is_last_fragment(event.id.entity(), &parents, &children);
+        let Ok((textbox, transition)) = textboxes.get(frag.textbox) else {
+            warn!("section played in a missing textbox, skipping");
+            if last {
+                lifecycle::finish_sequence(&mut commands, frag.textbox, event.id);
+            }
+            end_fragment(&mut commands, event.id, event.end(), frag.line_end.as_ref());
+            continue;
+        };
+        commands.send_event(UpdateNameplate::new(frag.textbox, frag.speaker.clone()));
+
+        let pages = section_pages(textbox, &frag.section);
+        spawn_page(
+            &mut commands,
+            textbox,
+            transition.is_some_and(TransitionState::is_opening),
+            SectionPages {
+                frag: Arc::new(frag),
+                pages: pages.into(),
+                page: 0,
+                commands: inline_commands.into(),
+                last,
+                id: event.id,
+                end: event.end(),
             },
         );
+    }
+}
 
-        let mut section = commands.entity(entity);
-        let mut section_commands = section.insert((
-            event.data.section.clone(),
-            Scroll::default(),
-            OnScrollEnd(on_end),
-        ));
-        (textboxes.get(textbox).unwrap().bundle.0)(&mut section_commands);
-        let child = section_commands.id();
-        commands.entity(textbox).add_child(child);
+/// Produces the section of `frag` as it is displayed, before pagination, along with the
+/// inline commands removed from its text.
+fn resolve_section(frag: &SectionFrag, world: &World) -> (TypeWriterSection, Vec<CommandMarker>) {
+    #[cfg(feature = "fluent")]
+    if let Some(section) = frag
+        .key
+        .as_ref()
+        .and_then(|key| localization::localize(key, world))
+    {
+        return command::extract_commands(&section, world);
     }
+
+    let section = match &frag.text {
+        Some(text) => (text.0)(world),
+        None => frag.section.clone(),
+    };
+    let (section, mut markers) = command::extract_commands(&section, world);
+    let Some(variables) = world.get_resource::<Variables>() else {
+        return (section, markers);
+    };
+
+    let mut indices = markers
+        .iter()
+        .map(|marker| marker.index)
+        .collect::<Vec<_>>();
+    let section = variables.format_section(&section, world, &mut indices);
+    for (marker, index) in markers.iter_mut().zip(indices) {
+        marker.index = index;
+    }
+    (section, markers)
 }
 
+fn section_pages(textbox: &TextBox, section: &TypeWriterSection) -> Vec<TypeWriterSection> {
+    match &textbox.text_area {
+        Some(area) => paginate::paginate(section, area),
+        None => vec![section.clone()],
+    }
+}
+
+/// The pages of a [`SectionFrag`], shown one after another in its [`TextBox`].
 #[derive(Clone)]
+struct SectionPages {
+    frag: Arc<SectionFrag>,
+    pages: Arc<[TypeWriterSection]>,
+    page: usize,
+    /// Inline commands of the whole section, indexed into the glyphs of every page.
+    commands: Arc<[CommandMarker]>,
+    /// Whether this is the last [`SectionFrag`] of its sequence.
+    last: bool,
+    id: FragmentId,
+    end: FragmentEndEvent,
+}
+
+/// Spawns the current page of `pages`.
+///
+/// A `held` page does not scroll until its [`TextBox`] finishes opening.
+fn spawn_page(commands: &mut Commands, textbox: &TextBox, held: bool, pages: SectionPages) {
+    let frag = pages.frag.clone();
+    let section = pages.pages[pages.page].clone();
+    let next = (pages.page + 1 < pages.pages.len()).then(|| SectionPages {
+        page: pages.page + 1,
+        ..pages.clone()
+    });
+    let textbox_entity = frag.textbox;
+    let end = pages.end;
+    let last = next.is_none() && pages.last;
+    let fragment = pages.id;
+    let kind = if last {
+        ContinueKind::End
+    } else {
+        ContinueKind::More
+    };
+
+    let start = pages.pages[..pages.page]
+        .iter()
+        .map(|page| page.text.chars().count())
+        .sum::<usize>();
+    let page_end = start + section.text.chars().count();
+    let pending = pages
+        .commands
+        .iter()
+        .filter(|marker| marker.index >= start && (marker.index < page_end || next.is_none()))
+        .cloned()
+        .map(|mut marker| {
+            marker.index -= start;
+            marker
+        })
+        .collect::<VecDeque<_>>();
+
+    let entity = commands.spawn_empty().id();
+    let on_clear = commands.register_system(
+        move |mut commands: Commands,
+              textboxes: Query<&TextBox>,
+              mut history: ResMut<DialogueHistory>,
+              time: Res<Time>,
+              mut continue_writer: EventWriter<UpdateContinueVis>| {
+            commands.entity(entity).despawn();
+            continue_writer.write(UpdateContinueVis::new(textbox_entity, Visibility::Hidden));
+            lifecycle::emit(
+                &mut commands,
+                textbox_entity,
+                SectionCleared {
+                    textbox: textbox_entity,
+                    section: entity,
+                    fragment,
+                },
+            );
+            match (&next, textboxes.get(textbox_entity)) {
+                (Some(next), Ok(textbox)) => {
+                    spawn_page(&mut commands, textbox, false, next.clone())
+                }
+                _ => {
+                    history.push(HistoryEntry::section(&frag, time.elapsed()));
+                    end_fragment(&mut commands, fragment, end, frag.line_end.as_ref());
+                    if last {
+                        lifecycle::emit(
+                            &mut commands,
+                            textbox_entity,
+                            SequenceFinished {
+                                textbox: textbox_entity,
+                                section: entity,
+                                fragment,
+                            },
+                        );
+                    }
+                }
+            }
+        },
+    );
+    let on_end = commands.register_system(
+        move |mut commands: Commands, mut writer: EventWriter<UpdateContinueVis>| {
+            commands
+                .entity(entity)
+                .insert((AwaitClear, OnClear(on_clear)));
+            writer
+                .write(UpdateContinueVis::new(textbox_entity, Visibility::Visible).with_kind(kind));
+            lifecycle::emit(
+                &mut commands,
+                textbox_entity,
+                SectionScrolled {
+                    textbox: textbox_entity,
+                    section: entity,
+                    fragment,
+                },
+            );
+            lifecycle::emit(
+                &mut commands,
+                textbox_entity,
+                SectionAwaitingInput {
+                    textbox: textbox_entity,
+                    section: entity,
+                    fragment,
+                },
+            );
+        },
+    );
+
+    let mut section_commands = commands.entity(entity);
+    if textbox.mode == TextBoxMode::Ui {
+        section_commands.insert(Node::default());
+    }
+    section_commands.insert((
+        AutoAdvanceTimer::new(&frag, &section),
+        section,
+        OnScrollEnd(on_end),
+        SectionSystems(vec![on_end, on_clear]),
+        BlipState::default(),
+    ));
+    if held {
+        section_commands.insert((HeldScroll, Visibility::Hidden));
+    } else {
+        section_commands.insert(Scroll::default());
+    }
+    if let Some(speaker) = &frag.speaker {
+        section_commands.insert(speaker.clone());
+    }
+    if !pending.is_empty() {
+        section_commands.insert(PendingCommands(pending));
+    }
+    #[cfg(feature = "fluent")]
+    if frag.key.is_some() {
+        section_commands.insert(localization::LocalizedPage(pages.clone()));
+    }
+    (textbox.bundle.0)(&mut section_commands);
+    commands.entity(textbox_entity).add_child(entity);
+    lifecycle::emit(
+        commands,
+        textbox_entity,
+        SectionStarted {
+            textbox: textbox_entity,
+            section: entity,
+            fragment,
+        },
+    );
+}
+
+/// A section of text shown in a [`TextBox`].
+///
+/// [`TypeWriterSection`] is not reflected, so the `section` is skipped by the type registry.
+#[derive(Clone, Reflect)]
+#[reflect(from_reflect = false)]
 pub struct SectionFrag {
+    /// The [`TextBox`] showing this section.
+    ///
+    /// [`Entity::PLACEHOLDER`] until the fragment is spawned, when it is taken from the
+    /// [`TextBoxEntity`] context.
+    pub textbox: Entity,
+    #[reflect(ignore)]
+    pub section: TypeWriterSection,
+    /// Overrides the [`AutoAdvance`] delay, in seconds.
+    pub auto_advance: Option<f32>,
+    pub speaker: Option<Speaker>,
+    /// Selects the speaker's portrait from [`Portraits`].
+    pub emotion: Option<Cow<'static, str>>,
+    #[reflect(ignore)]
+    text: Option<SectionText>,
+    /// Localization key resolved in place of the section.
+    #[cfg(feature = "fluent")]
+    key: Option<Cow<'static, str>>,
+    branch: bool,
+    #[reflect(ignore)]
+    line_end: Option<LineEnd>,
+}
+
+/// Produces the section of a [`SectionFrag`] when it is played.
+#[derive(Clone)]
+struct SectionText(Arc<dyn Fn(&World) -> TypeWriterSection + Send + Sync>);
+
+impl SectionFrag {
+    /// Creates a fragment whose [`TextBox`] is provided by the [`TextBoxEntity`] context.
+    pub fn new(section: impl Into<TypeWriterSection>) -> Self {
+        Self {
+            textbox: Entity::PLACEHOLDER,
+            section: section.into(),
+            auto_advance: None,
+            speaker: None,
+            emotion: None,
+            text: None,
+            #[cfg(feature = "fluent")]
+            key: None,
+            branch: false,
+            line_end: None,
+        }
+    }
+
+    /// Creates a fragment whose section is the message `key` in the active [`Localization`]
+    /// locale.
+    ///
+    /// The key itself is shown if the message is missing from every locale.
+    #[cfg(feature = "fluent")]
+    pub fn localized(key: impl Into<Cow<'static, str>>) -> Self {
+        let key = key.into();
+        Self {
+            key: Some(key.clone()),
+            ..Self::new(key.to_string())
+        }
+    }
+
+    /// Creates a fragment whose section is produced from the [`World`] each time it is
+    /// played.
+    ///
+    /// ```ignore
+    /// SectionFrag::from_world(|world: &World| {
+    ///     format!("You have {} gold.", world.resource::<Gold>().0)
+    /// })
+    /// ```
+    pub fn from_world<T: Into<TypeWriterSection>>(
+        text: impl Fn(&World) -> T + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            text: Some(SectionText(Arc::new(move |world| text(world).into()))),
+            ..Self::new("")
+        }
+    }
+
+    /// Creates a fragment shown in `textbox`, regardless of the [`TextBoxEntity`] context.
+    pub fn in_textbox(textbox: Entity, section: impl Into<TypeWriterSection>) -> Self {
+        Self {
+            textbox,
+            ..Self::new(section)
+        }
+    }
+}
+
+impl IntoFragment<SectionFrag, TextBoxEntity> for SectionFrag {
+    fn into_fragment(
+        self,
+        context: &Context<TextBoxEntity>,
+        commands: &mut Commands,
+    ) -> FragmentId {
+        let textbox = context.read().unwrap();
+        <_ as IntoFragment<SectionFrag, TextBoxEntity>>::into_fragment(
+            DataLeaf::new(SectionFrag {
+                textbox: if self.textbox == Entity::PLACEHOLDER {
+                    textbox.entity
+                } else {
+                    self.textbox
+                },
+                branch: textbox.branch,
+                ..self
+            }),
+            context,
+            commands,
+        )
+    }
+}
+
+/// Combinators for anything that converts into a [`SectionFrag`].
+pub trait SectionFragExt: Into<SectionFrag> {
+    /// Overrides the [`AutoAdvance`] delay for this section, in seconds.
+    fn auto_advance(self, delay: f32) -> SectionFrag {
+        SectionFrag {
+            auto_advance: Some(delay),
+            ..self.into()
+        }
+    }
+
+    /// Attributes this section to `speaker`, shown in the [`Nameplate`].
+    fn speaker(self, speaker: impl Into<Cow<'static, str>>) -> SectionFrag {
+        SectionFrag {
+            speaker: Some(Speaker::new(speaker)),
+            ..self.into()
+        }
+    }
+
+    /// Selects the speaker's `emotion` portrait from [`Portraits`].
+    fn emotion(self, emotion: impl Into<Cow<'static, str>>) -> SectionFrag {
+        SectionFrag {
+            emotion: Some(emotion.into()),
+            ..self.into()
+        }
+    }
+}
+
+impl<T: Into<SectionFrag>> SectionFragExt for T {}
+
+/// Continues a fragment that plays its lines one at a time, such as a [`ScriptFrag`], once
+/// the current line ends.
+///
+/// Each line is spawned as a sequence of its own. Rather than ending that sequence, the line
+/// despawns it and runs its `LineEnd`, so that finished lines do not accumulate.
+#[derive(Clone)]
+pub(crate) struct LineEnd(Arc<dyn Fn(&mut Commands) + Send + Sync>);
+
+impl LineEnd {
+    pub(crate) fn new(next: impl Fn(&mut Commands) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(next))
+    }
+}
+
+/// Plays `frag` as a line of a fragment in `textbox`, running `next` once it ends.
+///
+/// A `last` line ends the conversation.
+pub(crate) fn spawn_line<F: IntoLine>(
+    commands: &mut Commands,
     textbox: Entity,
-    section: TypeWriterSection,
+    last: bool,
+    frag: F,
+    next: impl Fn(&mut Commands) + Send + Sync + 'static,
+) {
+    let frag = frag.with_line_end(LineEnd::new(next));
+    spawn_root_with_context(
+        frag.always().once(),
+        TextBoxEntity::nested(textbox, last),
+        commands,
+    );
 }
 
-macro_rules! impl_into_frag {
-    ($ty:ty, $x:ident, $into:expr) => {
+/// A fragment that can be played as a line by [`spawn_line`].
+pub(crate) trait IntoLine: IntoFragment<SectionFrag, TextBoxEntity> + Sized {
+    fn with_line_end(self, line_end: LineEnd) -> Self;
+}
+
+impl IntoLine for SectionFrag {
+    fn with_line_end(self, line_end: LineEnd) -> Self {
+        Self {
+            line_end: Some(line_end),
+            ..self
+        }
+    }
+}
+
+/// Ends `fragment` by writing `end`, or, for a line, by despawning its sequence and running
+/// its [`LineEnd`].
+pub(crate) fn end_fragment(
+    commands: &mut Commands,
+    fragment: FragmentId,
+    end: FragmentEndEvent,
+    line_end: Option<&LineEnd>,
+) {
+    let Some(line_end) = line_end else {
+        commands.send_event(end);
+        return;
+    };
+    commands.queue(move |world: &mut World| {
+        let mut root = fragment.entity();
+        while let Some(child_of) = world.get::<ChildOf>(root) {
+            root = child_of.parent();
+        }
+        if let Ok(root) = world.get_entity_mut(root) {
+            root.despawn();
+        }
+    });
+    (line_end.0)(commands);
+}
+
+/// Ends a fragment that cannot play, finishing the sequence if it was the `last` fragment.
+pub(crate) fn skip_fragment(
+    commands: &mut Commands,
+    textbox: Entity,
+    fragment: FragmentId,
+    end: FragmentEndEvent,
+    last: bool,
+) {
+    if last {
+        lifecycle::finish_sequence(commands, textbox, fragment);
+    }
+    commands.send_event(end);
+}
+
+/// Events of a fragment that plays from an asset, such as a [`ScriptFrag`], along with the
+/// events held back while their asset loads.
+#[derive(SystemParam)]
+pub(crate) struct AssetFragments<'w, 's, T: Clone + Send + Sync + 'static, A: Asset> {
+    reader: EventReader<'w, 's, FragmentEvent<T>>,
+    held: Local<'s, Vec<FragmentEvent<T>>>,
+    assets: Res<'w, Assets<A>>,
+    asset_server: Res<'w, AssetServer>,
+}
+
+/// Whether the asset of a fragment can be played.
+pub(crate) enum AssetLoad<'a, A> {
+    Loaded(&'a A),
+    Loading,
+    Failed,
+}
+
+impl<T: Clone + Send + Sync + 'static, A: Asset> AssetFragments<'_, '_, T, A> {
+    /// Takes the events held back with [`AssetFragments::hold`], followed by those sent
+    /// since the last run.
+    pub fn read(&mut self) -> Vec<FragmentEvent<T>> {
+        let mut events = std::mem::take(&mut *self.held);
+        events.extend(self.reader.read().map(|event| FragmentEvent {
+            id: event.id,
+            data: event.data.clone(),
+        }));
+        events
+    }
+
+    pub fn get(&self, handle: &Handle<A>) -> AssetLoad<'_, A> {
+        match self.assets.get(handle) {
+            Some(asset) => AssetLoad::Loaded(asset),
+            None if matches!(self.asset_server.load_state(handle), LoadState::Failed(_)) => {
+                AssetLoad::Failed
+            }
+            None => AssetLoad::Loading,
+        }
+    }
+
+    /// Reads `event` again on the next run, once its asset may have loaded.
+    pub fn hold(&mut self, event: FragmentEvent<T>) {
+        self.held.push(event);
+    }
+}
+
+/// Implements `IntoFragment<SectionFrag, TextBoxEntity>` for a fragment with `textbox` and
+/// `branch` fields, filling them in from the [`TextBoxEntity`] context.
+macro_rules! impl_textbox_frag {
+    ($ty:ident) => {
         impl IntoFragment<SectionFrag, TextBoxEntity> for $ty {
             fn into_fragment(
                 self,
                 context: &Context<TextBoxEntity>,
                 commands: &mut Commands,
             ) -> FragmentId {
-                let $x = self;
-                <_ as IntoFragment<SectionFrag, TextBoxEntity>>::into_fragment(
-                    DataLeaf::new(SectionFrag {
-                        textbox: context.read().unwrap().0,
-                        section: $into,
+                let textbox = context.read().unwrap();
+                <_ as IntoFragment<$ty, TextBoxEntity>>::into_fragment(
+                    DataLeaf::new($ty {
+                        textbox: textbox.entity,
+                        branch: textbox.branch,
+                        ..self
                     }),
                     context,
                     commands,
@@ -187,7 +1014,102 @@ macro_rules! impl_into_frag {
         }
     };
 }
+pub(crate) use impl_textbox_frag;
+
+macro_rules! impl_into_frag {
+    ($ty:ty, $x:ident, $into:expr) => {
+        impl From<$ty> for SectionFrag {
+            fn from($x: $ty) -> Self {
+                SectionFrag::new($into)
+            }
+        }
+
+        impl IntoFragment<SectionFrag, TextBoxEntity> for $ty {
+            fn into_fragment(
+                self,
+                context: &Context<TextBoxEntity>,
+                commands: &mut Commands,
+            ) -> FragmentId {
+                SectionFrag::from(self).into_fragment(context, commands)
+            }
+        }
+    };
+}
 
-impl_into_frag!(&'static str, slf, slf.into());
-impl_into_frag!(String, slf, slf.into());
+impl_into_frag!(&'static str, slf, slf);
+impl_into_frag!(String, slf, slf);
 impl_into_frag!(TypeWriterSection, slf, slf);
+impl_into_frag!(SectionBuilder, slf, slf);
+impl_into_frag!(
+    Cow<'static, str>,
+    slf,
+    match slf {
+        Cow::Borrowed(text) => TypeWriterSection::from(text),
+        Cow::Owned(text) => TypeWriterSection::from(text),
+    }
+);
+impl_into_frag!(Arc<str>, slf, slf.to_string());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::{RunSystemOnce, SystemIdMarker};
+
+    fn registered_systems(world: &mut World) -> usize {
+        world
+            .query_filtered::<(), With<SystemIdMarker>>()
+            .iter(world)
+            .count()
+    }
+
+    /// Plays a section in `textbox`, scrolls it to the end and clears it.
+    fn play_section(world: &mut World, textbox: Entity) {
+        let id = FragmentId::new(world.spawn_empty().id());
+        world.send_event(FragmentEvent {
+            id,
+            data: SectionFrag::in_textbox(textbox, "Hello, World!"),
+        });
+        world.run_system_once(spawn_section_frags).unwrap();
+        world
+            .resource_mut::<Events<FragmentEvent<SectionFrag>>>()
+            .clear();
+
+        let section = world.get::<Children>(textbox).unwrap()[0];
+        let on_end = world.get::<OnScrollEnd>(section).unwrap().0;
+        world.run_system(on_end).unwrap();
+        let on_clear = world.get::<OnClear>(section).unwrap().0;
+        world.run_system(on_clear).unwrap();
+        world.despawn(id.entity());
+        world.flush();
+    }
+
+    #[test]
+    fn section_systems_do_not_accumulate() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<DialogueHistory>();
+        world.init_resource::<Events<FragmentEvent<SectionFrag>>>();
+        world.init_resource::<Events<FragmentEndEvent>>();
+        world.init_resource::<Events<UpdateContinueVis>>();
+        world.init_resource::<Events<UpdateNameplate>>();
+        world.init_resource::<Events<SectionStarted>>();
+        world.init_resource::<Events<SectionScrolled>>();
+        world.init_resource::<Events<SectionAwaitingInput>>();
+        world.init_resource::<Events<SectionCleared>>();
+        world.init_resource::<Events<SequenceFinished>>();
+        let textbox = world.spawn(TextBox::new(())).id();
+
+        play_section(&mut world, textbox);
+        let baseline = registered_systems(&mut world);
+        for _ in 0..5000 {
+            play_section(&mut world, textbox);
+        }
+
+        assert_eq!(registered_systems(&mut world), baseline);
+        assert!(
+            world
+                .get::<Children>(textbox)
+                .is_none_or(|children| children.is_empty())
+        );
+    }
+}
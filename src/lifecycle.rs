@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+use bevy_sequence::prelude::*;
+
+macro_rules! section_event {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(Event, Debug, Clone, Copy)]
+        pub struct $name {
+            pub textbox: Entity,
+            pub section: Entity,
+            pub fragment: FragmentId,
+        }
+    };
+}
+
+section_event!(
+    /// A section was spawned in its [`TextBox`](crate::TextBox).
+    ///
+    /// Sections split into pages start once for each page.
+    SectionStarted
+);
+
+section_event!(
+    /// A section finished scrolling, either on its own or with
+    /// [`FinishTextbox`](crate::FinishTextbox).
+    SectionScrolled
+);
+
+section_event!(
+    /// A section is waiting to be cleared by input or [`AutoAdvance`](crate::AutoAdvance).
+    SectionAwaitingInput
+);
+
+section_event!(
+    /// A section was cleared and despawned.
+    SectionCleared
+);
+
+section_event!(
+    /// A sequence ended, usually when its last section was cleared.
+    ///
+    /// A sequence that ends without a section, such as on a [`ChoiceOption`] without a
+    /// branch, or on a script that fails to load, finishes with [`Entity::PLACEHOLDER`] as
+    /// its `section`. The branches of a [`ChoiceOption`] finish the sequence only if the
+    /// choice was the last fragment of it, since otherwise the rest of the sequence follows
+    /// them.
+    ///
+    /// [`ChoiceOption`]: crate::ChoiceOption
+    SequenceFinished
+);
+
+/// Sends `event` as an [`Event`] and triggers it for observers of `textbox`.
+pub(crate) fn emit<E: Event + Clone>(commands: &mut Commands, textbox: Entity, event: E) {
+    commands.send_event(event.clone());
+    commands.trigger_targets(event, textbox);
+}
+
+/// Emits [`SequenceFinished`] for a sequence that ended in `textbox` without a section.
+pub(crate) fn finish_sequence(commands: &mut Commands, textbox: Entity, fragment: FragmentId) {
+    emit(
+        commands,
+        textbox,
+        SequenceFinished {
+            textbox,
+            section: Entity::PLACEHOLDER,
+            fragment,
+        },
+    );
+}
@@ -0,0 +1,350 @@
+use crate::{
+    MarkupEffects, SectionPages, TextBox, UpdateContinueVis, Value, Variables, choice::ChoiceList,
+    parse_markup, resolve_section, section_pages, spawn_page, transition::HeldScroll,
+};
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    platform::collections::HashMap,
+    prelude::*,
+};
+use bevy_pretty_text::prelude::*;
+use fluent::{FluentArgs, FluentResource, FluentValue, concurrent::FluentBundle};
+use std::{fmt, sync::Arc};
+use unic_langid::LanguageIdentifier;
+
+/// Fluent resources for each locale, and the locale that sections are shown in.
+///
+/// Sections created with [`SectionFrag::localized`](crate::SectionFrag::localized), and
+/// options created with [`ChoiceOption::localized`](crate::ChoiceOption::localized), are
+/// resolved when they are spawned, and again when the locale changes. Fluent variables
+/// such as `{ $gold }` are filled from the values set in [`Variables`], and translated
+/// strings may contain the markup supported by [`parse_markup`].
+///
+/// ```ignore
+/// app.insert_resource(
+///     Localization::new(langid!("en-US"))
+///         .with_resource(langid!("en-US"), asset_server.load("locales/en-US.ftl"))
+///         .with_resource(langid!("fr-FR"), asset_server.load("locales/fr-FR.ftl")),
+/// );
+///
+/// let frag = (SectionFrag::localized("greeting"), SectionFrag::localized("farewell"));
+/// ```
+#[derive(Resource)]
+pub struct Localization {
+    locale: LanguageIdentifier,
+    fallback: Option<LanguageIdentifier>,
+    resources: HashMap<LanguageIdentifier, Vec<Handle<FluentAsset>>>,
+    /// Bundles of the locales whose resources have all loaded, rebuilt when one changes.
+    bundles: HashMap<LanguageIdentifier, Arc<Bundle>>,
+}
+
+type Bundle = FluentBundle<Arc<FluentResource>>;
+
+impl Localization {
+    pub fn new(locale: LanguageIdentifier) -> Self {
+        Self {
+            locale,
+            fallback: None,
+            resources: HashMap::default(),
+            bundles: HashMap::default(),
+        }
+    }
+
+    /// Resolves keys missing from the active locale in `locale` instead.
+    pub fn with_fallback(mut self, locale: LanguageIdentifier) -> Self {
+        self.fallback = Some(locale);
+        self
+    }
+
+    pub fn with_resource(
+        mut self,
+        locale: LanguageIdentifier,
+        resource: Handle<FluentAsset>,
+    ) -> Self {
+        self.add_resource(locale, resource);
+        self
+    }
+
+    pub fn add_resource(&mut self, locale: LanguageIdentifier, resource: Handle<FluentAsset>) {
+        self.bundles.remove(&locale);
+        self.resources.entry(locale).or_default().push(resource);
+    }
+
+    pub fn locale(&self) -> &LanguageIdentifier {
+        &self.locale
+    }
+
+    /// Switches the active locale, re-resolving the sections currently shown.
+    pub fn set_locale(&mut self, locale: LanguageIdentifier) {
+        self.locale = locale;
+    }
+
+    /// Formats the message `key` in the active locale, or the fallback.
+    pub fn format(
+        &self,
+        key: &str,
+        args: Option<&FluentArgs>,
+        assets: &Assets<FluentAsset>,
+    ) -> Option<String> {
+        [Some(&self.locale), self.fallback.as_ref()]
+            .into_iter()
+            .flatten()
+            .find_map(|locale| self.format_in(locale, key, args, assets))
+    }
+
+    fn format_in(
+        &self,
+        locale: &LanguageIdentifier,
+        key: &str,
+        args: Option<&FluentArgs>,
+        assets: &Assets<FluentAsset>,
+    ) -> Option<String> {
+        let bundle = match self.bundles.get(locale) {
+            Some(bundle) => bundle.clone(),
+            None => Arc::new(self.build_bundle(locale, assets)?),
+        };
+
+        let pattern = bundle.get_message(key)?.value()?;
+        let mut errors = Vec::new();
+        let text = bundle.format_pattern(pattern, args, &mut errors);
+        for error in errors {
+            error!("{error} in message `{key}` of locale `{locale}`");
+        }
+        Some(text.into_owned())
+    }
+
+    /// Formats the message `key` with the values set in `variables` as Fluent variables.
+    fn format_key(
+        &self,
+        key: &str,
+        assets: &Assets<FluentAsset>,
+        variables: Option<&Variables>,
+    ) -> Option<String> {
+        let mut args = FluentArgs::new();
+        if let Some(variables) = variables {
+            for (name, value) in variables.iter() {
+                let value = match value {
+                    Value::Text(text) => FluentValue::from(text.to_string()),
+                    Value::Number(number) => FluentValue::from(*number),
+                    Value::Bool(value) => FluentValue::from(value.to_string()),
+                };
+                args.set(name.to_owned(), value);
+            }
+        }
+
+        let text = self.format(key, Some(&args), assets);
+        if text.is_none() {
+            error!("unknown message `{key}` in locale `{}`", self.locale);
+        }
+        text
+    }
+
+    fn build_bundle(
+        &self,
+        locale: &LanguageIdentifier,
+        assets: &Assets<FluentAsset>,
+    ) -> Option<Bundle> {
+        let mut bundle = FluentBundle::new_concurrent(vec![locale.clone()]);
+        bundle.set_use_isolating(false);
+        for handle in self.resources.get(locale)? {
+            match assets.get(handle) {
+                Some(resource) => {
+                    if let Err(errors) = bundle.add_resource(resource.0.clone()) {
+                        warn!("conflicting messages in `{locale}` resources: {errors:?}");
+                    }
+                }
+                None => warn!("`{locale}` resource used before it finished loading"),
+            }
+        }
+        Some(bundle)
+    }
+}
+
+/// Caches a bundle for each locale once its resources load, and rebuilds it when one of
+/// them is modified or removed.
+pub(crate) fn update_bundles(
+    mut localization: ResMut<Localization>,
+    assets: Res<Assets<FluentAsset>>,
+    mut events: EventReader<AssetEvent<FluentAsset>>,
+) {
+    // The cache does not change what sections show, so it does not mark the resource changed.
+    let localization = localization.bypass_change_detection();
+    for event in events.read() {
+        if let AssetEvent::Modified { id } | AssetEvent::Removed { id } = event {
+            let resources = &localization.resources;
+            localization.bundles.retain(|locale, _| {
+                resources
+                    .get(locale)
+                    .is_none_or(|handles| handles.iter().all(|handle| handle.id() != *id))
+            });
+        }
+    }
+
+    let stale = localization
+        .resources
+        .iter()
+        .filter(|(locale, handles)| {
+            !localization.bundles.contains_key(*locale)
+                && handles.iter().all(|handle| assets.contains(handle))
+        })
+        .map(|(locale, _)| locale.clone())
+        .collect::<Vec<_>>();
+    for locale in stale {
+        if let Some(bundle) = localization.build_bundle(&locale, &assets) {
+            localization.bundles.insert(locale, Arc::new(bundle));
+        }
+    }
+}
+
+/// A parsed Fluent (`.ftl`) file.
+#[derive(Asset, TypePath)]
+pub struct FluentAsset(Arc<FluentResource>);
+
+#[derive(Default)]
+pub(crate) struct FluentLoader;
+
+#[derive(Debug)]
+pub enum FluentLoadError {
+    Io(std::io::Error),
+    Utf8(std::string::FromUtf8Error),
+    Parse { path: String, errors: Vec<String> },
+}
+
+impl fmt::Display for FluentLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "could not read fluent resource: {error}"),
+            Self::Utf8(error) => write!(f, "fluent resource is not valid UTF-8: {error}"),
+            Self::Parse { path, errors } => {
+                write!(f, "could not parse `{path}`: {}", errors.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for FluentLoadError {}
+
+impl From<std::io::Error> for FluentLoadError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<std::string::FromUtf8Error> for FluentLoadError {
+    fn from(value: std::string::FromUtf8Error) -> Self {
+        Self::Utf8(value)
+    }
+}
+
+impl AssetLoader for FluentLoader {
+    type Asset = FluentAsset;
+    type Settings = ();
+    type Error = FluentLoadError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        match FluentResource::try_new(String::from_utf8(bytes)?) {
+            Ok(resource) => Ok(FluentAsset(Arc::new(resource))),
+            Err((_, errors)) => Err(FluentLoadError::Parse {
+                path: load_context.path().display().to_string(),
+                errors: errors.iter().map(ToString::to_string).collect(),
+            }),
+        }
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ftl"]
+    }
+}
+
+/// Resolves the localized text `key`, or `None` if it is missing from every locale.
+pub(crate) fn localize_text(key: &str, world: &World) -> Option<String> {
+    world.get_resource::<Localization>()?.format_key(
+        key,
+        world.resource::<Assets<FluentAsset>>(),
+        world.get_resource::<Variables>(),
+    )
+}
+
+/// Resolves the localized section `key`, or `None` if it is missing from every locale.
+pub(crate) fn localize(key: &str, world: &World) -> Option<TypeWriterSection> {
+    let text = localize_text(key, world)?;
+    let effects = world.resource::<MarkupEffects>();
+    match parse_markup(&text, effects) {
+        Ok(section) => Some(section),
+        Err(error) => {
+            error!("{error} in message `{key}`");
+            Some(TypeWriterSection::from(text))
+        }
+    }
+}
+
+/// The pages of a localized section, kept to re-resolve it when the locale changes.
+#[derive(Component)]
+pub(crate) struct LocalizedPage(pub(crate) SectionPages);
+
+pub(crate) fn relocalize_sections(
+    world: &World,
+    mut commands: Commands,
+    localization: Res<Localization>,
+    mut locale: Local<Option<LanguageIdentifier>>,
+    sections: Query<(Entity, &ChildOf, &LocalizedPage, Has<HeldScroll>)>,
+    textboxes: Query<&TextBox>,
+) {
+    if locale.as_ref() == Some(localization.locale()) {
+        return;
+    }
+    let first = locale.is_none();
+    *locale = Some(localization.locale().clone());
+    if first {
+        return;
+    }
+
+    for (entity, child_of, page, held) in sections.iter() {
+        let Ok(textbox) = textboxes.get(child_of.parent()) else {
+            continue;
+        };
+
+        let mut frag = (*page.0.frag).clone();
+        let (section, inline_commands) = resolve_section(&frag, world);
+        frag.section = section;
+        let pages = section_pages(textbox, &frag.section);
+
+        commands.entity(entity).despawn();
+        commands.send_event(UpdateContinueVis::new(frag.textbox, Visibility::Hidden));
+        spawn_page(
+            &mut commands,
+            textbox,
+            held,
+            SectionPages {
+                frag: Arc::new(frag),
+                page: page.0.page.min(pages.len() - 1),
+                pages: pages.into(),
+                commands: inline_commands.into(),
+                ..page.0.clone()
+            },
+        );
+    }
+}
+
+/// Re-resolves the localized options of the choices currently shown.
+pub(crate) fn relocalize_choices(
+    localization: Res<Localization>,
+    assets: Res<Assets<FluentAsset>>,
+    variables: Option<Res<Variables>>,
+    mut lists: Query<&mut ChoiceList>,
+    mut entries: Query<AnyOf<(&mut Text2d, &mut Text)>>,
+) {
+    for mut list in lists.iter_mut() {
+        list.relocalize(
+            |key| localization.format_key(key, &assets, variables.as_deref()),
+            &mut entries,
+        );
+    }
+}
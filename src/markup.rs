@@ -0,0 +1,361 @@
+use crate::SectionBuilder;
+use bevy::{color::palettes::css, platform::collections::HashMap, prelude::*};
+use bevy_pretty_text::prelude::*;
+use std::{borrow::Cow, fmt};
+
+/// Text effects and styles available by name in markup parsed at run time.
+///
+/// Effects default to `wave`. As in the `s!` macro, a style is the color of a span, and
+/// styles default to the basic CSS color names, such as `green` in `` `Hello|green` ``.
+#[derive(Resource, Debug, Clone)]
+pub struct MarkupEffects {
+    effects: HashMap<Cow<'static, str>, TextEffect>,
+    styles: HashMap<Cow<'static, str>, Color>,
+}
+
+impl Default for MarkupEffects {
+    fn default() -> Self {
+        let styles = [
+            ("black", css::BLACK),
+            ("silver", css::SILVER),
+            ("gray", css::GRAY),
+            ("white", css::WHITE),
+            ("maroon", css::MAROON),
+            ("red", css::RED),
+            ("purple", css::PURPLE),
+            ("fuchsia", css::FUCHSIA),
+            ("green", css::GREEN),
+            ("lime", css::LIME),
+            ("olive", css::OLIVE),
+            ("yellow", css::YELLOW),
+            ("navy", css::NAVY),
+            ("blue", css::BLUE),
+            ("teal", css::TEAL),
+            ("aqua", css::AQUA),
+        ];
+        Self {
+            effects: HashMap::from_iter([("wave".into(), TextEffect::Wave)]),
+            styles: styles
+                .into_iter()
+                .map(|(name, color)| (name.into(), color.into()))
+                .collect(),
+        }
+    }
+}
+
+impl MarkupEffects {
+    pub fn with(mut self, name: impl Into<Cow<'static, str>>, effect: TextEffect) -> Self {
+        self.insert(name, effect);
+        self
+    }
+
+    pub fn with_style(
+        mut self,
+        name: impl Into<Cow<'static, str>>,
+        color: impl Into<Color>,
+    ) -> Self {
+        self.insert_style(name, color);
+        self
+    }
+
+    pub fn insert(&mut self, name: impl Into<Cow<'static, str>>, effect: TextEffect) {
+        self.effects.insert(name.into(), effect);
+    }
+
+    pub fn insert_style(&mut self, name: impl Into<Cow<'static, str>>, color: impl Into<Color>) {
+        self.styles.insert(name.into(), color.into());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TextEffect> {
+        self.effects.get(name)
+    }
+
+    pub fn style(&self, name: &str) -> Option<Color> {
+        self.styles.get(name).copied()
+    }
+}
+
+/// An error in markup parsed at run time, at the glyph `index` of the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkupError {
+    pub index: usize,
+    pub kind: MarkupErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkupErrorKind {
+    UnclosedSpan,
+    UnclosedBracket,
+    UnknownEffect(String),
+    UnknownStyle(String),
+    InvalidPause(String),
+}
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at glyph {}", self.kind, self.index)
+    }
+}
+
+impl fmt::Display for MarkupErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnclosedSpan => write!(f, "unclosed '`'"),
+            Self::UnclosedBracket => write!(f, "unclosed `[`"),
+            Self::UnknownEffect(effect) => write!(f, "unknown effect `{effect}`"),
+            Self::UnknownStyle(style) => write!(f, "unknown style `{style}`"),
+            Self::InvalidPause(pause) => write!(f, "invalid pause `{pause}`"),
+        }
+    }
+}
+
+impl std::error::Error for MarkupError {}
+
+/// Parses a section from markup, for text that is not known at compile time.
+///
+/// The syntax of the `s!` macro is supported, with effects and styles looked up by name:
+/// - `` `World`[wave] `` applies the effects listed in brackets to a span.
+/// - `` `World|green` `` colors a span with a style.
+/// - `[0.5]` pauses the scroll, in seconds. A pause listed among the effects of a span, as in
+///   `` `Hello|green`[0.5] ``, follows the span.
+/// - `\` escapes the next character, including `` ` ``, `|` and `[` within a span.
+pub fn parse_markup(text: &str, effects: &MarkupEffects) -> Result<TypeWriterSection, MarkupError> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut builder = SectionBuilder::new();
+    let mut plain = String::new();
+
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                plain.push(chars.get(i + 1).copied().unwrap_or('\\'));
+                i += 2;
+            }
+            '`' => {
+                builder = builder.text(std::mem::take(&mut plain));
+                let (span, end) = delimited(&chars, i, '`', MarkupErrorKind::UnclosedSpan)?;
+                let color = match span.style {
+                    Some(style) => {
+                        Some(effects.style(style.trim()).ok_or_else(|| MarkupError {
+                            index: i + 1 + span.style_index,
+                            kind: MarkupErrorKind::UnknownStyle(style.trim().to_owned()),
+                        })?)
+                    }
+                    None => None,
+                };
+                let span = span.text;
+                i = end;
+
+                let mut span_effects = Vec::new();
+                let mut pauses = Vec::new();
+                if chars.get(i) == Some(&'[') {
+                    let (entries, end) =
+                        delimited(&chars, i, ']', MarkupErrorKind::UnclosedBracket)?;
+                    for entry in entries.text.split(',').map(str::trim) {
+                        if let Ok(seconds) = entry.parse::<f32>() {
+                            pauses.push(seconds);
+                            continue;
+                        }
+                        let effect = effects.get(entry).cloned().ok_or(MarkupError {
+                            index: i,
+                            kind: MarkupErrorKind::UnknownEffect(entry.to_owned()),
+                        })?;
+                        span_effects.push(effect);
+                    }
+                    i = end;
+                }
+
+                builder = builder.span(span_effects, color, span);
+                for seconds in pauses {
+                    builder = builder.pause(seconds);
+                }
+            }
+            '[' => {
+                builder = builder.text(std::mem::take(&mut plain));
+                let (pause, end) = delimited(&chars, i, ']', MarkupErrorKind::UnclosedBracket)?;
+                let seconds = pause.text.trim().parse::<f32>().map_err(|_| MarkupError {
+                    index: i,
+                    kind: MarkupErrorKind::InvalidPause(pause.text.clone()),
+                })?;
+                builder = builder.pause(seconds);
+                i = end;
+            }
+            c => {
+                plain.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    Ok(builder.text(plain).build())
+}
+
+/// The unescaped text between two delimiters, split at its last unescaped `|`.
+struct Delimited {
+    text: String,
+    style: Option<String>,
+    /// Glyph offset of the style from the opening delimiter.
+    style_index: usize,
+}
+
+/// Returns the text after `chars[start]` up to an unescaped `close`, and the index following
+/// `close`.
+fn delimited(
+    chars: &[char],
+    start: usize,
+    close: char,
+    kind: MarkupErrorKind,
+) -> Result<(Delimited, usize), MarkupError> {
+    let mut text = String::new();
+    let mut style: Option<(String, usize)> = None;
+    let mut i = start + 1;
+    loop {
+        let Some(&c) = chars.get(i) else {
+            return Err(MarkupError { index: start, kind });
+        };
+        match c {
+            '\\' => {
+                let escaped = chars.get(i + 1).copied().unwrap_or('\\');
+                match &mut style {
+                    Some((style, _)) => style.push(escaped),
+                    None => text.push(escaped),
+                }
+                i += 2;
+                continue;
+            }
+            c if c == close => break,
+            '|' if close == '`' => {
+                if let Some((previous, _)) = style.take() {
+                    text.push('|');
+                    text.push_str(&previous);
+                }
+                style = Some((String::new(), i - start));
+            }
+            c => match &mut style {
+                Some((style, _)) => style.push(c),
+                None => text.push(c),
+            },
+        }
+        i += 1;
+    }
+
+    let (style, style_index) = style.map_or((None, 0), |(style, index)| (Some(style), index));
+    Ok((
+        Delimited {
+            text,
+            style,
+            style_index,
+        },
+        i + 1,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<TypeWriterSection, MarkupError> {
+        parse_markup(text, &MarkupEffects::default())
+    }
+
+    fn effects(section: &TypeWriterSection) -> Vec<(usize, usize)> {
+        section
+            .effects
+            .iter()
+            .map(|effect| (effect.start, effect.end))
+            .collect()
+    }
+
+    fn pauses(section: &TypeWriterSection) -> Vec<(usize, f32)> {
+        section
+            .commands
+            .iter()
+            .filter_map(|command| match command.command {
+                TypeWriterCommand::Delay(seconds) => Some((command.index, seconds)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn plain_text() {
+        let section = parse("Hello, World!").unwrap();
+        assert_eq!(&*section.text, "Hello, World!");
+        assert!(section.effects.is_empty());
+        assert!(section.commands.is_empty());
+    }
+
+    #[test]
+    fn effects_apply_to_their_span() {
+        let section = parse("Hello, `World`[wave]!").unwrap();
+        assert_eq!(&*section.text, "Hello, World!");
+        assert_eq!(effects(&section), [(7, 12)]);
+        assert!(matches!(section.effects[0].effect, TextEffect::Wave));
+    }
+
+    fn colors(section: &TypeWriterSection) -> Vec<(usize, usize, Color)> {
+        section
+            .colors
+            .iter()
+            .map(|color| (color.start, color.end, color.color))
+            .collect()
+    }
+
+    #[test]
+    fn the_crate_example_parses_with_default_effects() {
+        let section = parse("`Hello|green`[0.5], `World`[wave]!").unwrap();
+        assert_eq!(&*section.text, "Hello, World!");
+        assert_eq!(pauses(&section), [(5, 0.5)]);
+        assert_eq!(effects(&section), [(7, 12)]);
+        assert_eq!(colors(&section), [(0, 5, Color::from(css::GREEN))]);
+    }
+
+    #[test]
+    fn styles_combine_with_effects() {
+        let effects = MarkupEffects::default().with_style("angry", css::ORANGE_RED);
+        let section = parse_markup("`Grr|angry`[wave]!", &effects).unwrap();
+        assert_eq!(&*section.text, "Grr!");
+        assert_eq!(self::effects(&section), [(0, 3)]);
+        assert_eq!(colors(&section), [(0, 3, Color::from(css::ORANGE_RED))]);
+    }
+
+    #[test]
+    fn standalone_pauses() {
+        let section = parse("Wait[1]... [0.25]now").unwrap();
+        assert_eq!(&*section.text, "Wait... now");
+        assert_eq!(pauses(&section), [(4, 1.), (8, 0.25)]);
+    }
+
+    #[test]
+    fn escapes() {
+        let section = parse(r"\`not a span\` \[1\] `a\|b\`c`").unwrap();
+        assert_eq!(&*section.text, "`not a span` [1] a|b`c");
+        assert!(section.commands.is_empty());
+    }
+
+    #[test]
+    fn only_the_last_bar_is_a_style() {
+        let section = parse("`a|b|green`").unwrap();
+        assert_eq!(&*section.text, "a|b");
+    }
+
+    #[test]
+    fn errors() {
+        let kind = |text| parse(text).unwrap_err().kind;
+        assert_eq!(kind("`Hello"), MarkupErrorKind::UnclosedSpan);
+        assert_eq!(kind("`Hello`[wave"), MarkupErrorKind::UnclosedBracket);
+        assert_eq!(kind("[0.5"), MarkupErrorKind::UnclosedBracket);
+        assert_eq!(
+            kind("`Hello`[shake]"),
+            MarkupErrorKind::UnknownEffect("shake".to_owned())
+        );
+        assert_eq!(
+            kind("`Hello|angry`"),
+            MarkupErrorKind::UnknownStyle("angry".to_owned())
+        );
+        assert_eq!(
+            kind("[soon]"),
+            MarkupErrorKind::InvalidPause("soon".to_owned())
+        );
+        assert_eq!(parse("Hi `there|angry`").unwrap_err().index, 10);
+    }
+}
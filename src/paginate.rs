@@ -0,0 +1,252 @@
+use bevy::prelude::*;
+use bevy_pretty_text::prelude::*;
+
+/// Area available to a section's text.
+///
+/// Sections are word wrapped into this area and split into pages when they do not fit,
+/// each page awaiting its own clear.
+///
+/// Pages are measured with a fixed advance per glyph rather than the font's metrics, so
+/// proportional fonts can fit more or fewer glyphs per line than estimated. Set
+/// `glyph_width` to the widest advance you expect to keep text from overflowing.
+#[derive(Debug, Clone, Copy)]
+pub struct TextArea {
+    pub size: Vec2,
+    /// Average horizontal advance of a glyph.
+    pub glyph_width: f32,
+    pub line_height: f32,
+}
+
+impl TextArea {
+    /// Estimates the glyph metrics from the font size.
+    pub fn new(size: Vec2, font_size: f32) -> Self {
+        Self {
+            size,
+            glyph_width: font_size * 0.6,
+            line_height: font_size * 1.2,
+        }
+    }
+
+    fn columns(&self) -> usize {
+        (self.size.x / self.glyph_width).floor().max(1.) as usize
+    }
+
+    fn rows(&self) -> usize {
+        (self.size.y / self.line_height).floor().max(1.) as usize
+    }
+}
+
+/// Splits `section` into pages that fit in `area`.
+pub(crate) fn paginate(section: &TypeWriterSection, area: &TextArea) -> Vec<TypeWriterSection> {
+    let chars = section.text.chars().collect::<Vec<_>>();
+    let mut starts = page_starts(&chars, area);
+    if starts.len() <= 1 {
+        return vec![section.clone()];
+    }
+
+    starts.push(chars.len());
+    starts
+        .windows(2)
+        .map(|page| slice_section(section, &chars, page[0], page[1]))
+        .collect()
+}
+
+/// Greedily word wraps `chars`, returning the index of the first glyph of every page.
+fn page_starts(chars: &[char], area: &TextArea) -> Vec<usize> {
+    let columns = area.columns();
+    let mut lines = vec![0];
+    let mut line_start = 0;
+    let mut last_space = None;
+
+    for (i, c) in chars.iter().enumerate() {
+        if *c == '\n' {
+            line_start = i + 1;
+            lines.push(line_start);
+            last_space = None;
+            continue;
+        }
+
+        if c.is_whitespace() {
+            last_space = Some(i);
+        }
+
+        if i - line_start >= columns {
+            line_start = match last_space {
+                Some(space) if space >= line_start => space + 1,
+                _ => i,
+            };
+            lines.push(line_start);
+            last_space = None;
+        }
+    }
+
+    lines
+        .into_iter()
+        .step_by(area.rows())
+        .filter(|start| *start < chars.len())
+        .collect()
+}
+
+/// Copies the glyphs in `start..end`, along with the commands, effects and colors that
+/// apply to them, rebased to the start of the page.
+///
+/// Effects and colors that span the page break are clipped so that they continue on the
+/// next page.
+fn slice_section(
+    section: &TypeWriterSection,
+    chars: &[char],
+    start: usize,
+    end: usize,
+) -> TypeWriterSection {
+    let last = end == chars.len();
+    let commands = section
+        .commands
+        .iter()
+        .filter(|command| command.index >= start && (command.index < end || last))
+        .cloned()
+        .map(|mut command| {
+            command.index -= start;
+            command
+        })
+        .collect::<Vec<_>>();
+    let effects = section
+        .effects
+        .iter()
+        .filter(|effect| effect.start < end && effect.end > start)
+        .cloned()
+        .map(|mut effect| {
+            effect.start = effect.start.max(start) - start;
+            effect.end = effect.end.min(end) - start;
+            effect
+        })
+        .collect::<Vec<_>>();
+    let colors = section
+        .colors
+        .iter()
+        .filter(|color| color.start < end && color.end > start)
+        .cloned()
+        .map(|mut color| {
+            color.start = color.start.max(start) - start;
+            color.end = color.end.min(end) - start;
+            color
+        })
+        .collect::<Vec<_>>();
+
+    let mut page = TypeWriterSection::from(chars[start..end].iter().collect::<String>());
+    page.commands = commands.into();
+    page.effects = effects.into();
+    page.colors = colors.into();
+    page
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SectionBuilder;
+    use bevy::color::palettes::css;
+
+    fn area(columns: usize, rows: usize) -> TextArea {
+        TextArea {
+            size: Vec2::new(columns as f32, rows as f32),
+            glyph_width: 1.,
+            line_height: 1.,
+        }
+    }
+
+    fn texts(pages: &[TypeWriterSection]) -> Vec<&str> {
+        pages.iter().map(|page| &*page.text).collect()
+    }
+
+    #[test]
+    fn empty_text_is_one_page() {
+        let pages = paginate(&TypeWriterSection::from(String::new()), &area(10, 1));
+        assert_eq!(texts(&pages), [""]);
+    }
+
+    #[test]
+    fn whitespace_is_one_page() {
+        let pages = paginate(&TypeWriterSection::from("   ".to_owned()), &area(10, 1));
+        assert_eq!(texts(&pages), ["   "]);
+    }
+
+    #[test]
+    fn short_text_is_one_page() {
+        let pages = paginate(&TypeWriterSection::from("Hello".to_owned()), &area(10, 2));
+        assert_eq!(texts(&pages), ["Hello"]);
+    }
+
+    #[test]
+    fn wraps_at_spaces() {
+        let section = TypeWriterSection::from("Hello there world".to_owned());
+        let pages = paginate(&section, &area(8, 1));
+        assert_eq!(texts(&pages), ["Hello ", "there ", "world"]);
+    }
+
+    #[test]
+    fn pages_cover_every_glyph() {
+        let text = "one two three four five six seven";
+        let pages = paginate(&TypeWriterSection::from(text.to_owned()), &area(6, 2));
+        assert!(pages.len() > 1);
+        assert_eq!(texts(&pages).concat(), text);
+    }
+
+    fn effects(page: &TypeWriterSection) -> Vec<(usize, usize)> {
+        page.effects
+            .iter()
+            .map(|effect| (effect.start, effect.end))
+            .collect()
+    }
+
+    fn colors(page: &TypeWriterSection) -> Vec<(usize, usize)> {
+        page.colors
+            .iter()
+            .map(|color| (color.start, color.end))
+            .collect()
+    }
+
+    fn pauses(page: &TypeWriterSection) -> Vec<(usize, f32)> {
+        page.commands
+            .iter()
+            .filter_map(|command| match command.command {
+                TypeWriterCommand::Delay(seconds) => Some((command.index, seconds)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spans_are_clipped_and_rebased_across_pages() {
+        let mut section = SectionBuilder::new()
+            .text("Hello th")
+            .pause(0.5)
+            .text("ere world")
+            .build();
+        section.effects = vec![IndexedTextEffect {
+            start: 3,
+            end: 11,
+            effect: TextEffect::Wave,
+        }]
+        .into();
+        section.colors = vec![IndexedTextColor {
+            start: 3,
+            end: 11,
+            color: css::GREEN.into(),
+        }]
+        .into();
+
+        let pages = paginate(&section, &area(8, 1));
+        assert_eq!(texts(&pages), ["Hello ", "there ", "world"]);
+
+        assert_eq!(effects(&pages[0]), [(3, 6)]);
+        assert_eq!(colors(&pages[0]), [(3, 6)]);
+        assert!(pauses(&pages[0]).is_empty());
+
+        assert_eq!(effects(&pages[1]), [(0, 5)]);
+        assert_eq!(colors(&pages[1]), [(0, 5)]);
+        assert_eq!(pauses(&pages[1]), [(2, 0.5)]);
+
+        assert!(effects(&pages[2]).is_empty());
+        assert!(colors(&pages[2]).is_empty());
+        assert!(pauses(&pages[2]).is_empty());
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{TextBox, TextBoxMode};
+use bevy::{prelude::*, render::view::RenderLayers};
+
+/// Ties a [`TextBox`] to one player of a local split-screen game.
+///
+/// Only `gamepad` advances the textbox and selects its choices, through the bindings of its
+/// [`TextboxInput`](crate::TextboxInput). Keyboard and mouse bindings are ignored, and the
+/// textbox is left out of [`TextboxFocus`](crate::TextboxFocus).
+///
+/// The textbox is shown through `camera`. A [`TextBoxMode::Sprite`] textbox and everything
+/// spawned in it, such as sections and choices, take the camera's [`RenderLayers`], and a
+/// [`TextBoxMode::Ui`] textbox is targeted at the camera with [`UiTargetCamera`].
+///
+/// ```ignore
+/// commands.spawn((
+///     Camera2d,
+///     Camera { viewport: Some(left_half), ..default() },
+///     RenderLayers::layer(1),
+/// ));
+/// commands.spawn((TextBox::new(bundle), TextboxPlayer::new(left_camera, gamepad)));
+/// ```
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextboxPlayer {
+    pub camera: Entity,
+    pub gamepad: Entity,
+}
+
+impl TextboxPlayer {
+    pub fn new(camera: Entity, gamepad: Entity) -> Self {
+        Self { camera, gamepad }
+    }
+}
+
+/// Shows player textboxes, and the entities spawned in them, through the player's camera.
+pub(crate) fn show_player_textboxes(
+    mut commands: Commands,
+    players: Query<(&TextboxPlayer, &TextBox)>,
+    changed: Query<Entity, Changed<TextboxPlayer>>,
+    cameras: Query<Option<&RenderLayers>, With<Camera>>,
+    spawned: Query<Entity, (Added<ChildOf>, Without<RenderLayers>)>,
+    parents: Query<&ChildOf>,
+    children: Query<&Children>,
+) {
+    let layers = |player: &TextboxPlayer| {
+        cameras
+            .get(player.camera)
+            .ok()
+            .flatten()
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    // A new or retargeted player textbox moves everything already spawned in it.
+    for root in changed.iter() {
+        let Ok((player, textbox)) = players.get(root) else {
+            continue;
+        };
+        match textbox.mode() {
+            TextBoxMode::Sprite => {
+                let layers = layers(player);
+                for entity in std::iter::once(root).chain(children.iter_descendants(root)) {
+                    commands.entity(entity).insert(layers.clone());
+                }
+            }
+            TextBoxMode::Ui => {
+                commands.entity(root).insert(UiTargetCamera(player.camera));
+            }
+        }
+    }
+
+    for entity in spawned.iter() {
+        let Some((player, textbox)) = parents
+            .iter_ancestors(entity)
+            .find_map(|ancestor| players.get(ancestor).ok())
+        else {
+            continue;
+        };
+        if textbox.mode() == TextBoxMode::Sprite {
+            commands.entity(entity).insert(layers(player));
+        }
+    }
+}
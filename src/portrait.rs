@@ -0,0 +1,172 @@
+use crate::{SectionFrag, Speaker, TextBox};
+use bevy::{platform::collections::HashMap, prelude::*};
+use bevy_sequence::prelude::*;
+use std::borrow::Cow;
+
+/// Displays the portrait of the active [`Speaker`].
+///
+/// Must be a child of a [`TextBox`] with a [`Sprite`], or an [`ImageNode`] in a UI
+/// textbox. The portrait is chosen from [`Portraits`] when each section spawns, and hidden
+/// when there is nothing to show.
+#[derive(Component)]
+pub struct Portrait;
+
+#[derive(Debug, Clone)]
+pub enum PortraitImage {
+    Image(Handle<Image>),
+    /// Index into the [`TextureAtlas`] already on the [`Portrait`] image.
+    AtlasIndex(usize),
+}
+
+impl From<Handle<Image>> for PortraitImage {
+    fn from(value: Handle<Image>) -> Self {
+        Self::Image(value)
+    }
+}
+
+impl From<usize> for PortraitImage {
+    fn from(value: usize) -> Self {
+        Self::AtlasIndex(value)
+    }
+}
+
+/// Maps speakers and their emotions to [`PortraitImage`]s.
+///
+/// Sections without an emotion, or with an emotion that is not registered, use the
+/// speaker's default portrait.
+#[derive(Resource, Default)]
+pub struct Portraits(HashMap<Speaker, SpeakerPortraits>);
+
+#[derive(Default)]
+struct SpeakerPortraits {
+    default: Option<PortraitImage>,
+    emotions: HashMap<Cow<'static, str>, PortraitImage>,
+}
+
+impl Portraits {
+    pub fn with_default(
+        mut self,
+        speaker: impl Into<Cow<'static, str>>,
+        image: impl Into<PortraitImage>,
+    ) -> Self {
+        self.insert_default(speaker, image);
+        self
+    }
+
+    pub fn with_emotion(
+        mut self,
+        speaker: impl Into<Cow<'static, str>>,
+        emotion: impl Into<Cow<'static, str>>,
+        image: impl Into<PortraitImage>,
+    ) -> Self {
+        self.insert_emotion(speaker, emotion, image);
+        self
+    }
+
+    pub fn insert_default(
+        &mut self,
+        speaker: impl Into<Cow<'static, str>>,
+        image: impl Into<PortraitImage>,
+    ) {
+        self.0.entry(Speaker::new(speaker)).or_default().default = Some(image.into());
+    }
+
+    pub fn insert_emotion(
+        &mut self,
+        speaker: impl Into<Cow<'static, str>>,
+        emotion: impl Into<Cow<'static, str>>,
+        image: impl Into<PortraitImage>,
+    ) {
+        self.0
+            .entry(Speaker::new(speaker))
+            .or_default()
+            .emotions
+            .insert(emotion.into(), image.into());
+    }
+
+    /// Returns the portrait for `speaker` with `emotion`, falling back to the speaker's
+    /// default.
+    pub fn get(&self, speaker: &Speaker, emotion: Option<&str>) -> Option<&PortraitImage> {
+        let Some(portraits) = self.0.get(speaker) else {
+            warn!("no portraits registered for speaker `{}`", speaker.0);
+            return None;
+        };
+
+        if let Some(emotion) = emotion {
+            match portraits.emotions.get(emotion) {
+                Some(image) => return Some(image),
+                None => warn!(
+                    "no `{emotion}` portrait registered for speaker `{}`, using default",
+                    speaker.0
+                ),
+            }
+        }
+
+        if portraits.default.is_none() {
+            warn!("no default portrait registered for speaker `{}`", speaker.0);
+        }
+        portraits.default.as_ref()
+    }
+}
+
+pub(crate) fn update_portraits(
+    mut reader: EventReader<FragmentEvent<SectionFrag>>,
+    portraits: Res<Portraits>,
+    textbox_query: Query<&Children, With<TextBox>>,
+    mut portrait_query: Query<
+        (Option<&mut Sprite>, Option<&mut ImageNode>, &mut Visibility),
+        With<Portrait>,
+    >,
+) {
+    for event in reader.read() {
+        let Ok(children) = textbox_query.get(event.data.textbox) else {
+            continue;
+        };
+
+        let image = event
+            .data
+            .speaker
+            .as_ref()
+            .and_then(|speaker| portraits.get(speaker, event.data.emotion.as_deref()));
+
+        for child in children.iter() {
+            let Ok((sprite, image_node, mut visibility)) = portrait_query.get_mut(child) else {
+                continue;
+            };
+
+            let Some(image) = image else {
+                *visibility = Visibility::Hidden;
+                continue;
+            };
+
+            let (handle, atlas) = match (sprite, image_node) {
+                (Some(sprite), _) => {
+                    let sprite = sprite.into_inner();
+                    (&mut sprite.image, &mut sprite.texture_atlas)
+                }
+                (None, Some(image_node)) => {
+                    let image_node = image_node.into_inner();
+                    (&mut image_node.image, &mut image_node.texture_atlas)
+                }
+                (None, None) => continue,
+            };
+
+            *visibility = match image {
+                PortraitImage::Image(image) => {
+                    *handle = image.clone();
+                    Visibility::Inherited
+                }
+                PortraitImage::AtlasIndex(index) => match atlas {
+                    Some(atlas) => {
+                        atlas.index = *index;
+                        Visibility::Inherited
+                    }
+                    None => {
+                        warn!("portrait atlas index {index} used without a texture atlas");
+                        Visibility::Hidden
+                    }
+                },
+            };
+        }
+    }
+}
@@ -0,0 +1,525 @@
+use crate::{
+    AssetFragments, AssetLoad, ChoiceFrag, ChoiceOption, MarkupEffects, MarkupErrorKind,
+    SectionFrag, SourceError, SourceErrorKind, Speaker, TextBoxEntity, is_last_fragment, lifecycle,
+    parse_markup, skip_fragment, spawn_line,
+};
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    prelude::*,
+};
+use bevy_pretty_text::prelude::*;
+use bevy_sequence::{fragment::DataLeaf, prelude::*};
+use std::{fmt, sync::Arc};
+
+/// A dialogue script loaded from a `.dialogue` file.
+///
+/// Each line is a section, optionally attributed to a speaker and emotion. Options start
+/// with `*`, and lines indented beneath an option are played when it is selected.
+/// Consecutive options form one choice. Text may contain the markup supported by
+/// [`parse_markup`], and lines starting with `#` are comments. Escape a colon that does not
+/// follow a speaker as `\:`.
+///
+/// ```text
+/// # A shopkeeper.
+/// Nic: Hello, `World`[wave]!
+/// Nic (happy): Would you like some?
+/// * Sure.
+///     Nic: Thank you!
+/// * No.
+/// The shop falls silent.
+/// ```
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct DialogueScript {
+    lines: Arc<[ScriptLine]>,
+}
+
+#[derive(Debug, Clone)]
+enum ScriptLine {
+    Section {
+        speaker: Option<String>,
+        emotion: Option<String>,
+        text: String,
+    },
+    Choice(Vec<ScriptOption>),
+}
+
+#[derive(Debug, Clone)]
+struct ScriptOption {
+    text: String,
+    branch: Arc<[ScriptLine]>,
+}
+
+/// Plays a [`DialogueScript`] in the [`TextBox`](crate::TextBox) provided by the
+/// [`TextBoxEntity`] context.
+///
+/// The script is looked up when the fragment plays, so with Bevy's `file_watcher` feature,
+/// edits to the file apply the next time it plays. A script that is still loading plays
+/// once it loads.
+///
+/// ```ignore
+/// let frag = (
+///     "Hello!",
+///     ScriptFrag::new(asset_server.load("shop.dialogue")),
+///     "Goodbye!",
+/// );
+/// ```
+#[derive(Clone)]
+pub struct ScriptFrag {
+    textbox: Entity,
+    source: ScriptSource,
+    branch: bool,
+}
+
+#[derive(Clone)]
+enum ScriptSource {
+    Asset(Handle<DialogueScript>),
+    Branch(Arc<[ScriptLine]>),
+}
+
+impl ScriptFrag {
+    pub fn new(script: Handle<DialogueScript>) -> Self {
+        Self {
+            textbox: Entity::PLACEHOLDER,
+            source: ScriptSource::Asset(script),
+            branch: false,
+        }
+    }
+}
+
+crate::impl_textbox_frag!(ScriptFrag);
+
+/// Plays [`ScriptFrag`]s, holding those whose script is still loading until it loads.
+pub(crate) fn play_scripts(
+    mut commands: Commands,
+    mut fragments: AssetFragments<ScriptFrag, DialogueScript>,
+    parents: Query<&ChildOf>,
+    children: Query<&Children>,
+) {
+    for event in fragments.read() {
+        let textbox = event.data.textbox;
+        let last = !event.data.branch && is_last_fragment(event.id.entity(), &parents, &children);
+        let lines = match &event.data.source {
+            ScriptSource::Asset(handle) => match fragments.get(handle) {
+                AssetLoad::Loaded(script) => script.lines.clone(),
+                AssetLoad::Loading => {
+                    fragments.hold(event);
+                    continue;
+                }
+                AssetLoad::Failed => {
+                    error!("dialogue script failed to load");
+                    skip_fragment(&mut commands, textbox, event.id, event.end(), last);
+                    continue;
+                }
+            },
+            ScriptSource::Branch(lines) => lines.clone(),
+        };
+        play_line(
+            &mut commands,
+            ScriptLines {
+                textbox,
+                lines,
+                last,
+                fragment: event.id,
+                end: event.end(),
+            },
+            0,
+        );
+    }
+}
+
+/// The lines of a playing [`ScriptFrag`].
+#[derive(Clone)]
+struct ScriptLines {
+    textbox: Entity,
+    lines: Arc<[ScriptLine]>,
+    /// Whether the [`ScriptFrag`] is the last fragment of its sequence, until a line
+    /// finishes the sequence.
+    last: bool,
+    fragment: FragmentId,
+    end: FragmentEndEvent,
+}
+
+/// Plays `lines[index]`, followed by the rest of `lines`, and then writes their `end`.
+fn play_line(commands: &mut Commands, lines: ScriptLines, index: usize) {
+    let Some(line) = lines.lines.get(index) else {
+        if lines.last {
+            lifecycle::finish_sequence(commands, lines.textbox, lines.fragment);
+        }
+        commands.send_event(lines.end);
+        return;
+    };
+
+    let textbox = lines.textbox;
+    let last = lines.last && index + 1 == lines.lines.len();
+    let next = {
+        let lines = ScriptLines {
+            last: lines.last && !last,
+            ..lines.clone()
+        };
+        move |commands: &mut Commands| play_line(commands, lines.clone(), index + 1)
+    };
+    match line {
+        ScriptLine::Section {
+            speaker,
+            emotion,
+            text,
+        } => {
+            let text = text.clone();
+            let mut frag = SectionFrag::from_world(move |world: &World| {
+                parse_markup(&text, world.resource::<MarkupEffects>()).unwrap_or_else(|error| {
+                    error!("{error} in dialogue script line `{text}`");
+                    TypeWriterSection::from(text.clone())
+                })
+            });
+            frag.speaker = speaker.clone().map(Speaker::new);
+            frag.emotion = emotion.clone().map(Into::into);
+            spawn_line(commands, textbox, last, frag, next);
+        }
+        ScriptLine::Choice(options) => {
+            let frag = ChoiceFrag::new(options.iter().map(|option| {
+                let choice = ChoiceOption::new(option.text.clone());
+                if option.branch.is_empty() {
+                    choice
+                } else {
+                    choice.then(ScriptFrag {
+                        textbox: Entity::PLACEHOLDER,
+                        source: ScriptSource::Branch(option.branch.clone()),
+                        branch: false,
+                    })
+                }
+            }));
+            spawn_line(commands, textbox, last, frag, next);
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct DialogueScriptLoader;
+
+/// An error in a [`DialogueScript`] file.
+pub type ScriptError = SourceError<ScriptErrorKind>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptErrorKind {
+    InvalidUtf8,
+    /// A line is indented without following an option.
+    UnexpectedIndent,
+    EmptyOption,
+    EmptySpeaker,
+    UnclosedEmotion,
+    Markup(MarkupErrorKind),
+}
+
+impl SourceErrorKind for ScriptErrorKind {
+    const FORMAT: &'static str = "dialogue script";
+    const INVALID_UTF8: Self = Self::InvalidUtf8;
+}
+
+impl fmt::Display for ScriptErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptErrorKind::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            ScriptErrorKind::UnexpectedIndent => write!(f, "unexpected indent"),
+            ScriptErrorKind::EmptyOption => write!(f, "option has no text"),
+            ScriptErrorKind::EmptySpeaker => write!(f, "speaker has no name"),
+            ScriptErrorKind::UnclosedEmotion => write!(f, "unclosed `(`"),
+            ScriptErrorKind::Markup(kind) => write!(f, "{kind}"),
+        }
+    }
+}
+
+impl AssetLoader for DialogueScriptLoader {
+    type Asset = DialogueScript;
+    type Settings = ();
+    type Error = ScriptError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let path = load_context.path().display().to_string();
+        ScriptError::parse(&path, bytes, parse_script)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["dialogue"]
+    }
+}
+
+fn parse_script(source: &str) -> Result<DialogueScript, ParseError> {
+    let lines = source
+        .lines()
+        .enumerate()
+        .map(|(number, line)| Line::new(number + 1, line))
+        .filter(|line| !line.text.is_empty() && !line.text.starts_with('#'))
+        .collect::<Vec<_>>();
+
+    let mut cursor = 0;
+    let lines = parse_block(&lines, &mut cursor, 0)?;
+    Ok(DialogueScript {
+        lines: lines.into(),
+    })
+}
+
+/// A non-empty line of a script, with its indentation in columns.
+struct Line<'a> {
+    number: usize,
+    indent: usize,
+    text: &'a str,
+}
+
+impl<'a> Line<'a> {
+    fn new(number: usize, line: &'a str) -> Self {
+        let text = line.trim_start();
+        let indent = line[..line.len() - text.len()]
+            .chars()
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum();
+        Self {
+            number,
+            indent,
+            text: text.trim_end(),
+        }
+    }
+
+    /// 1-based column of the byte `offset` into the line's text.
+    fn column(&self, offset: usize) -> usize {
+        self.indent + self.text[..offset].chars().count() + 1
+    }
+}
+
+type ParseError = (usize, usize, ScriptErrorKind);
+
+/// Parses lines starting at `cursor` until one is indented less than `indent`.
+fn parse_block(
+    lines: &[Line],
+    cursor: &mut usize,
+    indent: usize,
+) -> Result<Vec<ScriptLine>, ParseError> {
+    let mut block = Vec::new();
+    while let Some(line) = lines.get(*cursor) {
+        if line.indent < indent {
+            break;
+        }
+        if line.indent > indent {
+            return Err((line.number, 1, ScriptErrorKind::UnexpectedIndent));
+        }
+        *cursor += 1;
+
+        let Some(option) = line.text.strip_prefix('*') else {
+            block.push(parse_section(line)?);
+            continue;
+        };
+
+        let text = option.trim();
+        if text.is_empty() {
+            return Err((line.number, line.column(0), ScriptErrorKind::EmptyOption));
+        }
+        let branch = match lines.get(*cursor) {
+            Some(next) if next.indent > indent => parse_block(lines, cursor, next.indent)?,
+            _ => Vec::new(),
+        };
+        let option = ScriptOption {
+            text: text.to_owned(),
+            branch: branch.into(),
+        };
+        match block.last_mut() {
+            Some(ScriptLine::Choice(options)) => options.push(option),
+            _ => block.push(ScriptLine::Choice(vec![option])),
+        }
+    }
+    Ok(block)
+}
+
+/// Parses `Speaker (emotion): text`, where the speaker and emotion are optional.
+fn parse_section(line: &Line) -> Result<ScriptLine, ParseError> {
+    let (speaker, emotion, offset) = match line.text.split_once(':') {
+        Some((prefix, _)) if is_speaker(prefix) => {
+            let (name, emotion) = match prefix.split_once('(') {
+                Some((name, emotion)) => {
+                    let Some(emotion) = emotion.trim_end().strip_suffix(')') else {
+                        return Err((
+                            line.number,
+                            line.column(name.len()),
+                            ScriptErrorKind::UnclosedEmotion,
+                        ));
+                    };
+                    (name.trim(), Some(emotion.trim().to_owned()))
+                }
+                None => (prefix.trim(), None),
+            };
+            if name.is_empty() {
+                return Err((line.number, line.column(0), ScriptErrorKind::EmptySpeaker));
+            }
+            (Some(name.to_owned()), emotion, prefix.len() + 1)
+        }
+        _ => (None, None, 0),
+    };
+
+    let body = &line.text[offset..];
+    let text = body.trim_start();
+    let offset = offset + body.len() - text.len();
+    if let Err(error) = parse_markup(text, &MarkupEffects::default()) {
+        if !matches!(
+            error.kind,
+            MarkupErrorKind::UnknownEffect(_) | MarkupErrorKind::UnknownStyle(_)
+        ) {
+            let byte = text
+                .char_indices()
+                .nth(error.index)
+                .map_or(text.len(), |(byte, _)| byte);
+            return Err((
+                line.number,
+                line.column(offset + byte),
+                ScriptErrorKind::Markup(error.kind),
+            ));
+        }
+    }
+
+    Ok(ScriptLine::Section {
+        speaker,
+        emotion,
+        text: text.to_owned(),
+    })
+}
+
+/// Whether the text before a `:` names a speaker, rather than being part of the line.
+fn is_speaker(prefix: &str) -> bool {
+    !prefix.is_empty() && !prefix.contains(['`', '[', '\\'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SequenceFinished;
+    use bevy::ecs::system::RunSystemOnce;
+
+    fn parse(source: &str) -> Vec<ScriptLine> {
+        parse_script(source).unwrap().lines.to_vec()
+    }
+
+    fn error(source: &str) -> ParseError {
+        parse_script(source).unwrap_err()
+    }
+
+    fn section(line: &ScriptLine) -> (Option<&str>, Option<&str>, &str) {
+        let ScriptLine::Section {
+            speaker,
+            emotion,
+            text,
+        } = line
+        else {
+            panic!("expected a section, found {line:?}");
+        };
+        (speaker.as_deref(), emotion.as_deref(), text)
+    }
+
+    #[test]
+    fn sections() {
+        let lines = parse(
+            "# A comment.\nNic: Hello, `World`[wave]!\n\nNic (happy): Hi\nNo speaker here.\n",
+        );
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            section(&lines[0]),
+            (Some("Nic"), None, "Hello, `World`[wave]!")
+        );
+        assert_eq!(section(&lines[1]), (Some("Nic"), Some("happy"), "Hi"));
+        assert_eq!(section(&lines[2]), (None, None, "No speaker here."));
+    }
+
+    #[test]
+    fn colons_in_markup_and_escapes_are_not_speakers() {
+        let lines = parse("`Note: wave`[wave]\nTime\\: noon\n");
+        assert_eq!(section(&lines[0]).0, None);
+        assert_eq!(section(&lines[1]).0, None);
+    }
+
+    #[test]
+    fn consecutive_options_form_one_choice() {
+        let lines = parse("Buy?\n* Sure.\n    Nic: Thanks!\n    Bye.\n* No.\nDone.\n");
+        assert_eq!(lines.len(), 3);
+        let ScriptLine::Choice(options) = &lines[1] else {
+            panic!("expected a choice");
+        };
+        assert_eq!(options.len(), 2);
+        assert_eq!(options[0].text, "Sure.");
+        assert_eq!(options[0].branch.len(), 2);
+        assert_eq!(
+            section(&options[0].branch[0]),
+            (Some("Nic"), None, "Thanks!")
+        );
+        assert_eq!(options[1].text, "No.");
+        assert!(options[1].branch.is_empty());
+        assert_eq!(section(&lines[2]).2, "Done.");
+    }
+
+    #[test]
+    fn nested_choices() {
+        let lines = parse("* A\n\t* B\n\t\tDeep.\n\t* C\n* D\n");
+        let ScriptLine::Choice(options) = &lines[0] else {
+            panic!("expected a choice");
+        };
+        assert_eq!(options.len(), 2);
+        let ScriptLine::Choice(nested) = &options[0].branch[0] else {
+            panic!("expected a nested choice");
+        };
+        assert_eq!(nested.len(), 2);
+        assert_eq!(section(&nested[0].branch[0]).2, "Deep.");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            error("Hello.\n    Indented.\n"),
+            (2, 1, ScriptErrorKind::UnexpectedIndent)
+        );
+        assert_eq!(error("Buy?\n*\n"), (2, 1, ScriptErrorKind::EmptyOption));
+        assert_eq!(
+            error("(happy): Hi\n"),
+            (1, 1, ScriptErrorKind::EmptySpeaker)
+        );
+        assert_eq!(
+            error("Nic (happy: Hi\n"),
+            (1, 5, ScriptErrorKind::UnclosedEmotion)
+        );
+        assert!(matches!(
+            error("Nic: Hello `World\n"),
+            (1, _, ScriptErrorKind::Markup(_))
+        ));
+    }
+
+    #[test]
+    fn empty_scripts_finish_their_sequence() {
+        let mut world = World::new();
+        world.init_resource::<Events<SequenceFinished>>();
+        world.init_resource::<Events<FragmentEndEvent>>();
+        let textbox = world.spawn_empty().id();
+        let fragment = FragmentId::new(world.spawn_empty().id());
+        let end = FragmentEvent {
+            id: fragment,
+            data: ScriptFrag::new(Handle::default()),
+        }
+        .end();
+
+        world
+            .run_system_once(move |mut commands: Commands| {
+                let lines = ScriptLines {
+                    textbox,
+                    lines: Arc::new([]),
+                    last: true,
+                    fragment,
+                    end,
+                };
+                play_line(&mut commands, lines, 0);
+            })
+            .unwrap();
+
+        assert_eq!(world.resource::<Events<SequenceFinished>>().len(), 1);
+        assert_eq!(world.resource::<Events<FragmentEndEvent>>().len(), 1);
+    }
+}
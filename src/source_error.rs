@@ -0,0 +1,63 @@
+use std::fmt;
+
+/// An error loading a dialogue file, such as a [`DialogueScript`](crate::DialogueScript) or
+/// [`YarnProject`](crate::YarnProject), at a 1-based `line` and `column`.
+#[derive(Debug)]
+pub enum SourceError<K> {
+    Io(std::io::Error),
+    Parse {
+        path: String,
+        line: usize,
+        column: usize,
+        kind: K,
+    },
+}
+
+/// What went wrong in a [`SourceError::Parse`], for one file format.
+pub trait SourceErrorKind: fmt::Display + fmt::Debug {
+    /// The file format, as named in [`SourceError::Io`] messages.
+    const FORMAT: &'static str;
+    /// The kind of error for a file that is not UTF-8.
+    const INVALID_UTF8: Self;
+}
+
+impl<K: SourceErrorKind> SourceError<K> {
+    /// Parses the contents of the file at `path`, positioning the errors of `parse`.
+    pub(crate) fn parse<T>(
+        path: &str,
+        bytes: Vec<u8>,
+        parse: impl FnOnce(&str) -> Result<T, (usize, usize, K)>,
+    ) -> Result<T, Self> {
+        let error = |(line, column, kind)| Self::Parse {
+            path: path.to_owned(),
+            line,
+            column,
+            kind,
+        };
+
+        let source = String::from_utf8(bytes).map_err(|_| error((1, 1, K::INVALID_UTF8)))?;
+        parse(&source).map_err(error)
+    }
+}
+
+impl<K: SourceErrorKind> fmt::Display for SourceError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "could not read {}: {error}", K::FORMAT),
+            Self::Parse {
+                path,
+                line,
+                column,
+                kind,
+            } => write!(f, "{path}:{line}:{column}: {kind}"),
+        }
+    }
+}
+
+impl<K: SourceErrorKind> std::error::Error for SourceError<K> {}
+
+impl<K> From<std::io::Error> for SourceError<K> {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
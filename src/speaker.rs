@@ -0,0 +1,79 @@
+use crate::TextBox;
+use bevy::prelude::*;
+use std::borrow::Cow;
+
+/// The character speaking a section.
+///
+/// Inserted on the section entity when the section's fragment names a speaker.
+#[derive(Component, Debug, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Debug, PartialEq, Hash)]
+pub struct Speaker(pub Cow<'static, str>);
+
+impl Speaker {
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        Self(name.into())
+    }
+}
+
+/// Displays the name of the active [`Speaker`].
+///
+/// Must be a child of a [`TextBox`] with a [`Text2d`], or a [`Text`] in a UI textbox.
+/// Hidden while the active section has no speaker.
+#[derive(Component)]
+pub struct Nameplate;
+
+#[derive(Event, Debug, Clone, Reflect)]
+#[reflect(Debug)]
+pub struct UpdateNameplate {
+    entity: Entity,
+    speaker: Option<Speaker>,
+}
+
+impl UpdateNameplate {
+    pub fn new(entity: Entity, speaker: Option<Speaker>) -> Self {
+        Self { entity, speaker }
+    }
+
+    /// The [`TextBox`] whose [`Nameplate`]s are updated.
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    pub fn speaker(&self) -> Option<&Speaker> {
+        self.speaker.as_ref()
+    }
+}
+
+pub(crate) fn update_nameplates(
+    textbox_query: Query<&Children, With<TextBox>>,
+    mut nameplate_query: Query<
+        (Option<&mut Text2d>, Option<&mut Text>, &mut Visibility),
+        With<Nameplate>,
+    >,
+    mut reader: EventReader<UpdateNameplate>,
+) {
+    for event in reader.read() {
+        let Ok(children) = textbox_query.get(event.entity) else {
+            continue;
+        };
+
+        for child in children.iter() {
+            let Ok((text2d, text, mut visibility)) = nameplate_query.get_mut(child) else {
+                continue;
+            };
+
+            match &event.speaker {
+                Some(speaker) => {
+                    let text = text2d
+                        .map(|text2d| text2d.map_unchanged(|text2d| &mut text2d.0))
+                        .or_else(|| text.map(|text| text.map_unchanged(|text| &mut text.0)));
+                    if let Some(mut text) = text.filter(|text| **text != speaker.0) {
+                        *text = speaker.0.to_string();
+                    }
+                    *visibility = Visibility::Inherited;
+                }
+                None => *visibility = Visibility::Hidden,
+            }
+        }
+    }
+}
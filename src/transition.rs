@@ -0,0 +1,232 @@
+use crate::{TextBox, ui::offset_val};
+use bevy::{math::curve::EaseFunction, prelude::*};
+use bevy_pretty_text::prelude::*;
+
+/// Animates a [`TextBox`] as it opens and closes.
+///
+/// The open transition plays when the textbox is spawned, and its first section does not
+/// start scrolling until it finishes. Close the textbox with [`CloseTextbox`] to play the
+/// close transition before it is despawned.
+///
+/// Scale effects are applied to the [`Transform`], and fades to the [`Sprite`] or
+/// [`ImageNode`] color of the textbox entity only: its text, nameplate, portrait and
+/// continue indicator keep their own colors. Slides move the translation, or the `left`
+/// and `top` offsets of a [`TextBoxMode::Ui`](crate::TextBoxMode::Ui) textbox's [`Node`],
+/// since UI layout overwrites the translation of nodes but keeps their scale.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct TextBoxTransition {
+    pub open: Transition,
+    pub close: Transition,
+}
+
+impl TextBoxTransition {
+    /// Plays `transition` to open, and in reverse to close.
+    pub fn symmetric(transition: Transition) -> Self {
+        Self {
+            open: transition,
+            close: transition,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Transition {
+    pub effect: TransitionEffect,
+    /// Duration in seconds.
+    pub duration: f32,
+    pub ease: EaseFunction,
+}
+
+impl Transition {
+    pub fn new(effect: TransitionEffect, duration: f32) -> Self {
+        Self {
+            effect,
+            duration,
+            ease: EaseFunction::CubicOut,
+        }
+    }
+
+    pub fn with_ease(mut self, ease: EaseFunction) -> Self {
+        self.ease = ease;
+        self
+    }
+
+    /// Progress of the transition, where `0` is closed and `1` is open.
+    fn progress(&self, elapsed: f32) -> f32 {
+        let t = if self.duration > 0. {
+            (elapsed / self.duration).min(1.)
+        } else {
+            1.
+        };
+        self.ease.sample_clamped(t)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum TransitionEffect {
+    Scale,
+    /// Fades the alpha of the textbox's own [`Sprite`] or [`ImageNode`], but not its
+    /// children.
+    Fade,
+    /// Slides in from this offset, relative to where the textbox was spawned.
+    Slide(Vec2),
+}
+
+/// Closes a [`TextBox`], playing its [`TextBoxTransition`] before it is despawned.
+///
+/// A textbox without a transition is despawned immediately.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CloseTextbox(pub Entity);
+
+#[derive(Component)]
+pub(crate) struct TransitionState {
+    phase: Phase,
+    elapsed: f32,
+    base: Transform,
+    /// The `left` and `top` offsets of a [`Node`] textbox.
+    base_offset: Option<(Val, Val)>,
+    alpha: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Opening,
+    Open,
+    Closing,
+}
+
+impl TransitionState {
+    pub fn is_opening(&self) -> bool {
+        self.phase == Phase::Opening
+    }
+}
+
+/// Scroll of a section held back until its textbox finishes opening.
+#[derive(Component)]
+pub(crate) struct HeldScroll;
+
+pub(crate) fn start_transitions(
+    mut commands: Commands,
+    textboxes: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&Node>,
+            Option<&Sprite>,
+            Option<&ImageNode>,
+        ),
+        Added<TextBoxTransition>,
+    >,
+) {
+    for (entity, transform, node, sprite, image_node) in textboxes.iter() {
+        let alpha = sprite
+            .map(|sprite| sprite.color.alpha())
+            .or(image_node.map(|image_node| image_node.color.alpha()))
+            .unwrap_or(1.);
+
+        commands.entity(entity).insert(TransitionState {
+            phase: Phase::Opening,
+            elapsed: 0.,
+            base: *transform,
+            base_offset: node.map(|node| (node.left, node.top)),
+            alpha,
+        });
+    }
+}
+
+pub(crate) fn close_textboxes(
+    mut commands: Commands,
+    mut reader: EventReader<CloseTextbox>,
+    mut textboxes: Query<Option<&mut TransitionState>, With<TextBox>>,
+) {
+    for event in reader.read() {
+        match textboxes.get_mut(event.0) {
+            Ok(Some(mut state)) => {
+                if state.phase != Phase::Closing {
+                    state.phase = Phase::Closing;
+                    state.elapsed = 0.;
+                }
+            }
+            Ok(None) => commands.entity(event.0).despawn(),
+            Err(_) => {}
+        }
+    }
+}
+
+pub(crate) fn animate_transitions(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut textboxes: Query<(
+        Entity,
+        &TextBoxTransition,
+        &mut TransitionState,
+        &mut Transform,
+        Option<&mut Node>,
+        Option<&mut Sprite>,
+        Option<&mut ImageNode>,
+        Option<&Children>,
+    )>,
+    held: Query<(), With<HeldScroll>>,
+) {
+    for (entity, transition, mut state, mut transform, node, sprite, image_node, children) in
+        textboxes.iter_mut()
+    {
+        if state.phase == Phase::Open {
+            continue;
+        }
+
+        state.elapsed += time.delta_secs();
+        let (transition, progress) = match state.phase {
+            Phase::Open => continue,
+            Phase::Opening => (transition.open, transition.open.progress(state.elapsed)),
+            Phase::Closing => (
+                transition.close,
+                1. - transition.close.progress(state.elapsed),
+            ),
+        };
+
+        match transition.effect {
+            TransitionEffect::Scale => transform.scale = state.base.scale * progress,
+            TransitionEffect::Slide(offset) => {
+                let offset = offset * (1. - progress);
+                match (node, state.base_offset) {
+                    // UI y points down.
+                    (Some(mut node), Some((left, top))) => {
+                        node.left = offset_val(left, offset.x);
+                        node.top = offset_val(top, -offset.y);
+                    }
+                    _ => transform.translation = state.base.translation + offset.extend(0.),
+                }
+            }
+            TransitionEffect::Fade => {
+                let alpha = state.alpha * progress;
+                if let Some(mut sprite) = sprite {
+                    sprite.color.set_alpha(alpha);
+                }
+                if let Some(mut image_node) = image_node {
+                    image_node.color.set_alpha(alpha);
+                }
+            }
+        }
+
+        if state.elapsed < transition.duration {
+            continue;
+        }
+
+        match state.phase {
+            Phase::Opening => {
+                state.phase = Phase::Open;
+                for child in children.into_iter().flatten() {
+                    if held.contains(*child) {
+                        commands
+                            .entity(*child)
+                            .remove::<HeldScroll>()
+                            .insert((Scroll::default(), Visibility::Inherited));
+                    }
+                }
+            }
+            Phase::Closing => commands.entity(entity).despawn(),
+            Phase::Open => {}
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+/// The window size that UI textboxes are authored for.
+///
+/// When present, [`UiScale`] is kept at the ratio between the primary window and this
+/// resolution so that [`TextBoxMode::Ui`](crate::TextBoxMode::Ui) textboxes, along with
+/// the rest of the UI, keep their proportions at any window size.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct UiReferenceResolution(pub Vec2);
+
+pub(crate) fn scale_ui(
+    reference: Res<UiReferenceResolution>,
+    windows: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
+    mut scale: ResMut<UiScale>,
+) {
+    let Ok(window) = windows.single() else {
+        return;
+    };
+
+    let ratio = window.size() / reference.0;
+    let new_scale = ratio.min_element();
+    if new_scale.is_finite() && scale.0 != new_scale {
+        scale.0 = new_scale;
+    }
+}
+
+/// Moves a [`Node`] position by `offset` logical pixels.
+///
+/// Only pixel and automatic positions can be offset, so other units are left as they are.
+pub(crate) fn offset_val(val: Val, offset: f32) -> Val {
+    match val {
+        Val::Auto => Val::Px(offset),
+        Val::Px(px) => Val::Px(px + offset),
+        val => {
+            warn_once!("only `Val::Px` and `Val::Auto` node positions can be animated");
+            val
+        }
+    }
+}
@@ -0,0 +1,505 @@
+use bevy::{platform::collections::HashMap, prelude::*};
+use bevy_pretty_text::prelude::*;
+use std::{borrow::Cow, fmt, sync::Arc};
+
+/// Values interpolated into section text when each section is displayed.
+///
+/// Placeholders are written as `{name}`, optionally followed by a format:
+/// - `{gold:group}` groups the digits of a number, e.g. `1,234,567`.
+/// - `{gold:plural(coin,coins)}` picks a word by whether the number is one.
+///
+/// Write `{{` and `}}` for literal braces. Unknown variables are logged as errors and left
+/// in the text as written.
+///
+/// ```ignore
+/// app.insert_resource(
+///     Variables::default()
+///         .with("player_name", "Nic")
+///         .with_source(|name: &str, world: &World| {
+///             (name == "gold").then(|| world.resource::<Gold>().0.into())
+///         }),
+/// );
+///
+/// let frag = "You have {gold:group} {gold:plural(coin,coins)}, {player_name}.";
+/// ```
+#[derive(Resource, Default, Clone)]
+pub struct Variables {
+    values: HashMap<Cow<'static, str>, Value>,
+    sources: Vec<Arc<dyn VariableSource>>,
+}
+
+/// Resolves variables that are not stored in [`Variables`] from the [`World`].
+pub trait VariableSource: Send + Sync + 'static {
+    fn resolve(&self, name: &str, world: &World) -> Option<Value>;
+}
+
+impl<F> VariableSource for F
+where
+    F: Fn(&str, &World) -> Option<Value> + Send + Sync + 'static,
+{
+    fn resolve(&self, name: &str, world: &World) -> Option<Value> {
+        self(name, world)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(Cow<'static, str>),
+    Number(f64),
+    Bool(bool),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text(text) => f.write_str(text),
+            Self::Number(number) => write!(f, "{number}"),
+            Self::Bool(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+impl From<&'static str> for Value {
+    fn from(value: &'static str) -> Self {
+        Self::Text(value.into())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::Text(value.into())
+    }
+}
+
+impl From<Cow<'static, str>> for Value {
+    fn from(value: Cow<'static, str>) -> Self {
+        Self::Text(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+macro_rules! impl_from_number {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(value: $ty) -> Self {
+                    Self::Number(value as f64)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_number!(f32, f64, i32, i64, u32, u64, usize);
+
+/// An error in a placeholder of a section's text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariableError {
+    Unknown(String),
+    UnknownFormat {
+        name: String,
+        format: String,
+    },
+    /// A number format was applied to a text value.
+    NotANumber {
+        name: String,
+        format: String,
+    },
+    /// A `{` at this glyph index is never closed.
+    Unclosed(usize),
+}
+
+impl fmt::Display for VariableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown(name) => write!(f, "unknown variable `{name}`"),
+            Self::UnknownFormat { name, format } => {
+                write!(f, "unknown format `{format}` for variable `{name}`")
+            }
+            Self::NotANumber { name, format } => {
+                write!(
+                    f,
+                    "format `{format}` requires variable `{name}` to be a number"
+                )
+            }
+            Self::Unclosed(index) => write!(f, "unclosed `{{` at glyph {index}"),
+        }
+    }
+}
+
+impl std::error::Error for VariableError {}
+
+impl Variables {
+    pub fn with(mut self, name: impl Into<Cow<'static, str>>, value: impl Into<Value>) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    pub fn with_source(mut self, source: impl VariableSource) -> Self {
+        self.add_source(source);
+        self
+    }
+
+    pub fn set(&mut self, name: impl Into<Cow<'static, str>>, value: impl Into<Value>) {
+        self.values.insert(name.into(), value.into());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Value> {
+        self.values.remove(name)
+    }
+
+    /// Iterates over the values set in the store, without consulting sources.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_ref(), value))
+    }
+
+    /// Adds a source consulted, in order, for variables that are not set.
+    pub fn add_source(&mut self, source: impl VariableSource) {
+        self.sources.push(Arc::new(source));
+    }
+
+    /// Returns the value of `name`, preferring values set in the store over sources.
+    pub fn resolve(&self, name: &str, world: &World) -> Option<Value> {
+        self.values.get(name).cloned().or_else(|| {
+            self.sources
+                .iter()
+                .find_map(|source| source.resolve(name, world))
+        })
+    }
+
+    /// Expands the placeholders in `text`, returning the first error.
+    pub fn format(&self, text: &str, world: &World) -> Result<String, VariableError> {
+        let (replacements, mut errors) = self.expand(text, world);
+        if errors.is_empty() {
+            Ok(apply(text, &replacements))
+        } else {
+            Err(errors.swap_remove(0))
+        }
+    }
+
+    /// Expands the placeholders in `section`, moving its commands, effects and colors, and
+    /// the glyph `indices`, along with the glyphs they apply to.
+    pub(crate) fn format_section(
+        &self,
+        section: &TypeWriterSection,
+        world: &World,
+        indices: &mut [usize],
+    ) -> TypeWriterSection {
+        if !section.text.contains(['{', '}']) {
+            return section.clone();
+        }
+
+        let (replacements, errors) = self.expand(&section.text, world);
+        for error in errors {
+            error!("{error} in section `{}`", section.text);
+        }
+        replace_section(section, &replacements, indices)
+    }
+
+    fn expand(&self, text: &str, world: &World) -> (Vec<Replacement>, Vec<VariableError>) {
+        let chars = text.chars().collect::<Vec<_>>();
+        let mut replacements = Vec::new();
+        let mut errors = Vec::new();
+
+        let mut i = 0;
+        while i < chars.len() {
+            match (chars[i], chars.get(i + 1)) {
+                ('{', Some('{')) | ('}', Some('}')) => {
+                    replacements.push(Replacement::new(i, i + 2, chars[i].to_string()));
+                    i += 2;
+                }
+                ('{', _) => {
+                    let Some(len) = chars[i + 1..].iter().position(|c| *c == '}') else {
+                        errors.push(VariableError::Unclosed(i));
+                        break;
+                    };
+                    let end = i + len + 2;
+                    let body = chars[i + 1..end - 1].iter().collect::<String>();
+                    match self.placeholder(&body, world) {
+                        Ok(value) => replacements.push(Replacement::new(i, end, value)),
+                        Err(error) => errors.push(error),
+                    }
+                    i = end;
+                }
+                _ => i += 1,
+            }
+        }
+
+        (replacements, errors)
+    }
+
+    fn placeholder(&self, body: &str, world: &World) -> Result<String, VariableError> {
+        let (name, format) = match body.split_once(':') {
+            Some((name, format)) => (name.trim(), Some(format.trim())),
+            None => (body.trim(), None),
+        };
+        let value = self
+            .resolve(name, world)
+            .ok_or_else(|| VariableError::Unknown(name.to_owned()))?;
+
+        let Some(format) = format else {
+            return Ok(value.to_string());
+        };
+        let number = match value {
+            Value::Number(number) => number,
+            Value::Text(_) | Value::Bool(_) => {
+                return Err(VariableError::NotANumber {
+                    name: name.to_owned(),
+                    format: format.to_owned(),
+                });
+            }
+        };
+
+        if format == "group" {
+            return Ok(group(number));
+        }
+        if let Some((one, other)) = format
+            .strip_prefix("plural(")
+            .and_then(|args| args.strip_suffix(')'))
+            .and_then(|args| args.split_once(','))
+        {
+            let word = if number == 1. { one } else { other };
+            return Ok(word.trim().to_owned());
+        }
+
+        Err(VariableError::UnknownFormat {
+            name: name.to_owned(),
+            format: format.to_owned(),
+        })
+    }
+}
+
+/// Applies `replacements` to `section`, moving its commands, effects and colors, and the
+/// glyph `indices`, along with the glyphs they apply to.
+pub(crate) fn replace_section(
+    section: &TypeWriterSection,
+    replacements: &[Replacement],
+    indices: &mut [usize],
+) -> TypeWriterSection {
+    let commands = section
+        .commands
+        .iter()
+        .cloned()
+        .map(|mut command| {
+            command.index = remap(command.index, replacements);
+            command
+        })
+        .collect::<Vec<_>>();
+    let effects = section
+        .effects
+        .iter()
+        .cloned()
+        .map(|mut effect| {
+            effect.start = remap(effect.start, replacements);
+            effect.end = remap(effect.end, replacements);
+            effect
+        })
+        .collect::<Vec<_>>();
+    let colors = section
+        .colors
+        .iter()
+        .cloned()
+        .map(|mut color| {
+            color.start = remap(color.start, replacements);
+            color.end = remap(color.end, replacements);
+            color
+        })
+        .collect::<Vec<_>>();
+    for index in indices {
+        *index = remap(*index, replacements);
+    }
+
+    TypeWriterSection {
+        text: apply(&section.text, replacements).into(),
+        commands: commands.into(),
+        effects: effects.into(),
+        colors: colors.into(),
+        ..section.clone()
+    }
+}
+
+/// Replaces the glyphs in `start..end` with `text`.
+pub(crate) struct Replacement {
+    start: usize,
+    end: usize,
+    text: String,
+    len: usize,
+}
+
+impl Replacement {
+    pub(crate) fn new(start: usize, end: usize, text: String) -> Self {
+        let len = text.chars().count();
+        Self {
+            start,
+            end,
+            text,
+            len,
+        }
+    }
+}
+
+fn apply(text: &str, replacements: &[Replacement]) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut replacements = replacements.iter().peekable();
+    let mut chars = text.chars().enumerate();
+    while let Some((i, c)) = chars.next() {
+        match replacements.next_if(|replacement| replacement.start == i) {
+            Some(replacement) => {
+                result.push_str(&replacement.text);
+                chars.nth(replacement.end - replacement.start - 2);
+            }
+            None => result.push(c),
+        }
+    }
+    result
+}
+
+/// Maps a glyph index in the original text to the expanded text.
+///
+/// Indices inside a placeholder move to the start of its value.
+fn remap(index: usize, replacements: &[Replacement]) -> usize {
+    let mut index = index as isize;
+    let mut shift = 0;
+    for replacement in replacements {
+        let (start, end) = (replacement.start as isize, replacement.end as isize);
+        if index >= end {
+            shift += replacement.len as isize - (end - start);
+        } else {
+            if index > start {
+                index = start;
+            }
+            break;
+        }
+    }
+    (index + shift) as usize
+}
+
+/// Formats `number` with its integer digits grouped in thousands.
+fn group(number: f64) -> String {
+    let text = Value::Number(number).to_string();
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(text) => ("-", text),
+        None => ("", text.as_str()),
+    };
+    let (integer, fraction) = match text.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (text, None),
+    };
+
+    let mut grouped = String::from(sign);
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        grouped.push('.');
+        grouped.push_str(fraction);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(variables: &Variables, text: &str) -> Result<String, VariableError> {
+        variables.format(text, &World::new())
+    }
+
+    #[test]
+    fn placeholders() {
+        let variables = Variables::default()
+            .with("player_name", "Nic")
+            .with("gold", 1234567)
+            .with("coins", 1)
+            .with_source(|name: &str, _: &World| (name == "level").then_some(Value::Number(3.)));
+
+        assert_eq!(
+            format(&variables, "Hi {player_name}, level {level}."),
+            Ok("Hi Nic, level 3.".to_owned())
+        );
+        assert_eq!(
+            format(&variables, "{gold:group} {gold:plural(coin, coins)}"),
+            Ok("1,234,567 coins".to_owned())
+        );
+        assert_eq!(
+            format(&variables, "{coins} {coins:plural(coin,coins)}"),
+            Ok("1 coin".to_owned())
+        );
+        assert_eq!(
+            format(&variables, "{{player_name}}"),
+            Ok("{player_name}".to_owned())
+        );
+    }
+
+    #[test]
+    fn store_is_preferred_over_sources() {
+        let variables = Variables::default()
+            .with("name", "Nic")
+            .with_source(|_: &str, _: &World| Some("Source".into()));
+        assert_eq!(format(&variables, "{name}"), Ok("Nic".to_owned()));
+        assert_eq!(format(&variables, "{other}"), Ok("Source".to_owned()));
+    }
+
+    #[test]
+    fn errors() {
+        let variables = Variables::default().with("name", "Nic").with("gold", 5);
+        assert_eq!(
+            format(&variables, "{missing}"),
+            Err(VariableError::Unknown("missing".to_owned()))
+        );
+        assert_eq!(
+            format(&variables, "{name:group}"),
+            Err(VariableError::NotANumber {
+                name: "name".to_owned(),
+                format: "group".to_owned(),
+            })
+        );
+        assert_eq!(
+            format(&variables, "{gold:upper}"),
+            Err(VariableError::UnknownFormat {
+                name: "gold".to_owned(),
+                format: "upper".to_owned(),
+            })
+        );
+        assert_eq!(
+            format(&variables, "Hi {name"),
+            Err(VariableError::Unclosed(3))
+        );
+    }
+
+    #[test]
+    fn grouping() {
+        assert_eq!(group(0.), "0");
+        assert_eq!(group(999.), "999");
+        assert_eq!(group(1000.), "1,000");
+        assert_eq!(group(-1234567.5), "-1,234,567.5");
+    }
+
+    #[test]
+    fn indices_follow_their_glyphs() {
+        let text = "{a}b{long}c";
+        let replacements = [
+            Replacement::new(0, 3, "xyz".to_owned()),
+            Replacement::new(4, 10, String::new()),
+        ];
+        assert_eq!(apply(text, &replacements), "xyzbc");
+        // `b`, inside the removed placeholder, and `c`.
+        assert_eq!(remap(3, &replacements), 3);
+        assert_eq!(remap(6, &replacements), 4);
+        assert_eq!(remap(10, &replacements), 4);
+    }
+}
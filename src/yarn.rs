@@ -0,0 +1,1227 @@
+use crate::{
+    AssetFragments, AssetLoad, ChoiceFrag, ChoiceOption, SectionFrag, SourceError, SourceErrorKind,
+    Speaker, TextBoxEntity, Value, Variables, is_last_fragment, lifecycle, skip_fragment,
+    spawn_line,
+};
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    platform::collections::HashMap,
+    prelude::*,
+};
+use bevy_sequence::{fragment::DataLeaf, prelude::*};
+use std::{fmt, sync::Arc};
+
+/// Nodes imported from a Yarn Spinner (`.yarn`) file.
+///
+/// Lines, speaker prefixes, options, `<<jump>>`, `<<stop>>`, `<<set>>`, `<<declare>>` and
+/// `<<if>>` blocks are supported, along with conditions on lines and options. Variables are
+/// stored in [`Variables`] without their `$`, so `{$gold}` in a line is interpolated like
+/// `{gold}`. Any other command is sent as a [`YarnCommand`].
+///
+/// Constructs that cannot be imported, such as markup, functions and line groups, fail the
+/// import with a [`YarnError`] instead of being dropped.
+#[derive(Asset, TypePath, Debug)]
+pub struct YarnProject {
+    nodes: Arc<HashMap<String, Block>>,
+    declarations: Arc<[(String, Expr)]>,
+}
+
+type Block = Arc<[Statement]>;
+
+#[derive(Debug)]
+enum Statement {
+    Line {
+        speaker: Option<String>,
+        text: String,
+    },
+    Options(Vec<YarnOption>),
+    Set {
+        name: String,
+        value: Expr,
+    },
+    If(Vec<(Option<Expr>, Block)>),
+    Jump(String),
+    Stop,
+    Command {
+        name: String,
+        args: Vec<String>,
+    },
+}
+
+#[derive(Debug)]
+struct YarnOption {
+    text: String,
+    condition: Option<Arc<Expr>>,
+    body: Block,
+}
+
+/// A custom `<<command>>` reached while running a [`YarnProject`].
+///
+/// Sent as an [`Event`] and triggered for observers of the [`TextBox`](crate::TextBox).
+/// The dialogue continues immediately.
+#[derive(Event, Debug, Clone)]
+pub struct YarnCommand {
+    pub textbox: Entity,
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+/// Runs a node of a [`YarnProject`] in the [`TextBox`](crate::TextBox) provided by the
+/// [`TextBoxEntity`] context.
+///
+/// A project that is still loading runs once it loads.
+///
+/// ```ignore
+/// let frag = YarnFrag::new(asset_server.load("village.yarn"), "Start");
+/// spawn_root_with_context(frag, TextBoxEntity::new(textbox), &mut commands);
+/// ```
+#[derive(Clone)]
+pub struct YarnFrag {
+    textbox: Entity,
+    source: YarnSource,
+    branch: bool,
+}
+
+#[derive(Clone)]
+enum YarnSource {
+    Node {
+        project: Handle<YarnProject>,
+        node: String,
+    },
+    /// Continues a runner after an option is selected.
+    Runner(Runner),
+}
+
+impl YarnFrag {
+    pub fn new(project: Handle<YarnProject>, node: impl Into<String>) -> Self {
+        Self {
+            textbox: Entity::PLACEHOLDER,
+            source: YarnSource::Node {
+                project,
+                node: node.into(),
+            },
+            branch: false,
+        }
+    }
+}
+
+crate::impl_textbox_frag!(YarnFrag);
+
+/// The position of a running [`YarnFrag`].
+#[derive(Clone)]
+struct Runner {
+    project: Arc<HashMap<String, Block>>,
+    textbox: Entity,
+    frames: Vec<(Block, usize)>,
+    fragment: FragmentId,
+    end: Option<FragmentEndEvent>,
+    /// Whether the [`YarnFrag`] is the last fragment of its sequence, until a line
+    /// finishes the sequence.
+    last: bool,
+}
+
+/// Plays [`YarnFrag`]s, holding those whose project is still loading until it loads.
+pub(crate) fn play_yarn(
+    mut commands: Commands,
+    mut fragments: AssetFragments<YarnFrag, YarnProject>,
+    parents: Query<&ChildOf>,
+    children: Query<&Children>,
+) {
+    for event in fragments.read() {
+        let textbox = event.data.textbox;
+        let end = event.end();
+        let last = !event.data.branch && is_last_fragment(event.id.entity(), &parents, &children);
+        let runner = match &event.data.source {
+            YarnSource::Runner(runner) => Runner {
+                fragment: event.id,
+                end: Some(end),
+                last,
+                ..runner.clone()
+            },
+            YarnSource::Node { project, node } => {
+                let project = match fragments.get(project) {
+                    AssetLoad::Loaded(project) => project,
+                    AssetLoad::Loading => {
+                        fragments.hold(event);
+                        continue;
+                    }
+                    AssetLoad::Failed => {
+                        error!("yarn project failed to load");
+                        skip_fragment(&mut commands, textbox, event.id, end, last);
+                        continue;
+                    }
+                };
+                let Some(block) = project.nodes.get(node) else {
+                    error!("unknown yarn node `{node}`");
+                    skip_fragment(&mut commands, textbox, event.id, end, last);
+                    continue;
+                };
+
+                let declarations = project.declarations.clone();
+                commands.queue(move |world: &mut World| declare(world, &declarations));
+                Runner {
+                    project: project.nodes.clone(),
+                    textbox,
+                    frames: vec![(block.clone(), 0)],
+                    fragment: event.id,
+                    end: Some(end),
+                    last,
+                }
+            }
+        };
+        commands.queue(move |world: &mut World| step(world, runner));
+    }
+}
+
+/// Sets declared variables that are not already set.
+fn declare(world: &mut World, declarations: &[(String, Expr)]) {
+    for (name, value) in declarations {
+        let variables = world.resource::<Variables>();
+        if variables.resolve(name, world).is_some() {
+            continue;
+        }
+        match value.eval(variables, world) {
+            Ok(value) => world.resource_mut::<Variables>().set(name.clone(), value),
+            Err(error) => error!("{error} in declaration of `${name}`"),
+        }
+    }
+}
+
+/// Runs statements until one that waits for the player, or the end of the dialogue.
+fn step(world: &mut World, mut runner: Runner) {
+    loop {
+        let Some((block, index)) = runner.frames.last_mut() else {
+            if runner.last {
+                lifecycle::finish_sequence(&mut world.commands(), runner.textbox, runner.fragment);
+                world.flush();
+            }
+            if let Some(end) = runner.end {
+                world.send_event(end);
+            }
+            return;
+        };
+        if *index >= block.len() {
+            runner.frames.pop();
+            continue;
+        }
+        let block = block.clone();
+        let statement = &block[*index];
+        *index += 1;
+
+        match statement {
+            Statement::Line { speaker, text } => {
+                let mut frag = SectionFrag::new(text.clone());
+                frag.speaker = speaker.clone().map(Speaker::new);
+                let textbox = runner.textbox;
+                let last = runner.last && !waits(&runner.project, &runner.frames);
+                runner.last &= !last;
+                spawn_line(
+                    &mut world.commands(),
+                    textbox,
+                    last,
+                    frag,
+                    move |commands: &mut Commands| {
+                        let runner = runner.clone();
+                        commands.queue(move |world: &mut World| step(world, runner));
+                    },
+                );
+                world.flush();
+                return;
+            }
+            Statement::Options(options) => {
+                let end = runner.end;
+                let frag = ChoiceFrag::new(options.iter().map(|option| {
+                    let mut branch = runner.clone();
+                    branch.frames.push((option.body.clone(), 0));
+                    let choice = ChoiceOption::new(option.text.clone()).then(YarnFrag {
+                        textbox: Entity::PLACEHOLDER,
+                        source: YarnSource::Runner(branch),
+                        branch: false,
+                    });
+                    match &option.condition {
+                        Some(condition) => {
+                            let condition = condition.clone();
+                            choice.visible_if(move |world: &World| condition.holds(world))
+                        }
+                        None => choice,
+                    }
+                }));
+                // The selected branch runs the rest of the dialogue before the choice ends.
+                spawn_line(
+                    &mut world.commands(),
+                    runner.textbox,
+                    runner.last,
+                    frag,
+                    move |commands: &mut Commands| {
+                        if let Some(end) = end {
+                            commands.send_event(end);
+                        }
+                    },
+                );
+                world.flush();
+                return;
+            }
+            Statement::Set { name, value } => {
+                let variables = world.resource::<Variables>();
+                match value.eval(variables, world) {
+                    Ok(value) => world.resource_mut::<Variables>().set(name.clone(), value),
+                    Err(error) => error!("{error} in `<<set ${name}>>`"),
+                }
+            }
+            Statement::If(branches) => {
+                if let Some((_, body)) = branches.iter().find(|(condition, _)| {
+                    condition
+                        .as_ref()
+                        .is_none_or(|condition| condition.holds(world))
+                }) {
+                    runner.frames.push((body.clone(), 0));
+                }
+            }
+            Statement::Jump(node) => {
+                runner.frames.clear();
+                runner.frames.push((runner.project[node].clone(), 0));
+            }
+            Statement::Stop => runner.frames.clear(),
+            Statement::Command { name, args } => {
+                let command = YarnCommand {
+                    textbox: runner.textbox,
+                    name: name.clone(),
+                    args: args.clone(),
+                };
+                world.send_event(command.clone());
+                world.trigger_targets(command, runner.textbox);
+            }
+        }
+    }
+}
+
+/// Whether the statements left in `frames` may wait for the player again.
+///
+/// The conditions of `<<if>>` blocks are only evaluated when they run, so a block waits if
+/// any of its branches contains a line or options.
+fn waits(project: &HashMap<String, Block>, frames: &[(Block, usize)]) -> bool {
+    let mut jumped = Vec::new();
+    frames
+        .iter()
+        .rev()
+        .find_map(|(block, index)| statements_wait(project, &block[*index..], &mut jumped))
+        .unwrap_or(false)
+}
+
+/// Whether `statements` wait for the player, or `None` if they run to their end without
+/// waiting.
+fn statements_wait<'a>(
+    project: &'a HashMap<String, Block>,
+    statements: &'a [Statement],
+    jumped: &mut Vec<&'a str>,
+) -> Option<bool> {
+    for statement in statements {
+        match statement {
+            Statement::Line { .. } | Statement::Options(_) => return Some(true),
+            Statement::Set { .. } | Statement::Command { .. } => {}
+            Statement::If(branches) => {
+                if branches
+                    .iter()
+                    .any(|(_, body)| statements_wait(project, body, jumped) == Some(true))
+                {
+                    return Some(true);
+                }
+            }
+            Statement::Jump(node) => {
+                // A node that jumps back without waiting never ends.
+                if jumped.contains(&node.as_str()) {
+                    return Some(false);
+                }
+                jumped.push(node);
+                return Some(statements_wait(project, &project[node], jumped).unwrap_or(false));
+            }
+            Statement::Stop => return Some(false),
+        }
+    }
+    None
+}
+
+#[derive(Default)]
+pub(crate) struct YarnLoader;
+
+/// An error importing a [`YarnProject`].
+pub type YarnError = SourceError<YarnErrorKind>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum YarnErrorKind {
+    InvalidUtf8,
+    MissingTitle,
+    DuplicateNode(String),
+    /// A node's header is not followed by `---`.
+    UnterminatedHeader,
+    /// A node's body is not followed by `===`.
+    UnterminatedNode,
+    UnknownNode(String),
+    UnclosedCommand,
+    /// An `<<elseif>>`, `<<else>>` or `<<endif>>` without a matching `<<if>>`.
+    UnexpectedCommand(String),
+    UnclosedIf,
+    EmptyOption,
+    InvalidCommand(String),
+    InvalidExpression(String),
+    Unsupported(String),
+}
+
+impl SourceErrorKind for YarnErrorKind {
+    const FORMAT: &'static str = "yarn file";
+    const INVALID_UTF8: Self = Self::InvalidUtf8;
+}
+
+impl fmt::Display for YarnErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            YarnErrorKind::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            YarnErrorKind::MissingTitle => write!(f, "node has no title"),
+            YarnErrorKind::DuplicateNode(node) => write!(f, "node `{node}` is defined twice"),
+            YarnErrorKind::UnterminatedHeader => write!(f, "node header is not closed by `---`"),
+            YarnErrorKind::UnterminatedNode => write!(f, "node is not closed by `===`"),
+            YarnErrorKind::UnknownNode(node) => write!(f, "jump to unknown node `{node}`"),
+            YarnErrorKind::UnclosedCommand => write!(f, "unclosed `<<`"),
+            YarnErrorKind::UnexpectedCommand(command) => {
+                write!(f, "`<<{command}>>` without a matching `<<if>>`")
+            }
+            YarnErrorKind::UnclosedIf => write!(f, "`<<if>>` is not closed by `<<endif>>`"),
+            YarnErrorKind::EmptyOption => write!(f, "option has no text"),
+            YarnErrorKind::InvalidCommand(command) => write!(f, "invalid `<<{command}>>`"),
+            YarnErrorKind::InvalidExpression(error) => write!(f, "invalid expression: {error}"),
+            YarnErrorKind::Unsupported(construct) => write!(f, "{construct} are not supported"),
+        }
+    }
+}
+
+impl AssetLoader for YarnLoader {
+    type Asset = YarnProject;
+    type Settings = ();
+    type Error = YarnError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let path = load_context.path().display().to_string();
+        YarnError::parse(&path, bytes, parse_project)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["yarn"]
+    }
+}
+
+type ParseError = (usize, usize, YarnErrorKind);
+
+/// A line of a node's body, without comments or hashtags.
+struct Line<'a> {
+    number: usize,
+    indent: usize,
+    text: &'a str,
+}
+
+impl Line<'_> {
+    fn error(&self, offset: usize, kind: YarnErrorKind) -> ParseError {
+        (self.number, self.indent + offset + 1, kind)
+    }
+}
+
+fn parse_project(source: &str) -> Result<YarnProject, ParseError> {
+    let mut nodes = HashMap::default();
+    let mut declarations = Vec::new();
+    let mut jumps = Vec::new();
+    let mut lines = source.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+    while let Some((number, line)) = lines.next() {
+        if line.trim().is_empty() || line.trim_start().starts_with("//") {
+            continue;
+        }
+
+        let mut title = None;
+        let mut header = Some((number, line));
+        while let Some((number, line)) = header {
+            let line = line.trim();
+            if line == "---" {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("title:") {
+                title = Some(value.trim().to_owned());
+            }
+            header = lines.next();
+            if header.is_none() {
+                return Err((number, 1, YarnErrorKind::UnterminatedHeader));
+            }
+        }
+        let Some(title) = title else {
+            return Err((number, 1, YarnErrorKind::MissingTitle));
+        };
+
+        let mut body = Vec::new();
+        loop {
+            let Some((number, line)) = lines.next() else {
+                return Err((number, 1, YarnErrorKind::UnterminatedNode));
+            };
+            if line.trim() == "===" {
+                break;
+            }
+            if let Some(line) = body_line(number, line) {
+                body.push(line);
+            }
+        }
+
+        let mut parser = Parser {
+            lines: &body,
+            cursor: 0,
+            declarations: &mut declarations,
+            jumps: &mut jumps,
+        };
+        let block = parser.block(0)?;
+        if let Some(line) = body.get(parser.cursor) {
+            return Err(line.error(
+                0,
+                YarnErrorKind::UnexpectedCommand(command_name(line.text).to_owned()),
+            ));
+        }
+        if nodes.insert(title.clone(), block.into()).is_some() {
+            return Err((number, 1, YarnErrorKind::DuplicateNode(title)));
+        }
+    }
+
+    for (node, number, column) in jumps {
+        if !nodes.contains_key(&node) {
+            return Err((number, column, YarnErrorKind::UnknownNode(node)));
+        }
+    }
+    Ok(YarnProject {
+        nodes: Arc::new(nodes),
+        declarations: declarations.into(),
+    })
+}
+
+/// Strips indentation, comments and hashtags from a body line, skipping it if nothing
+/// remains.
+fn body_line(number: usize, line: &str) -> Option<Line<'_>> {
+    let text = line.trim_start();
+    let indent = line.len() - text.len();
+    let mut end = text.len();
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '/' if text[i..].starts_with("//") => {
+                end = i;
+                break;
+            }
+            '#' if i == 0 || text[..i].ends_with(char::is_whitespace) => {
+                end = i;
+                break;
+            }
+            _ => {}
+        }
+    }
+    let text = text[..end].trim_end();
+    (!text.is_empty()).then_some(Line {
+        number,
+        indent,
+        text,
+    })
+}
+
+fn command_name(text: &str) -> &str {
+    text.trim_start_matches("<<")
+        .split(|c: char| c.is_whitespace() || c == '>')
+        .next()
+        .unwrap_or_default()
+}
+
+struct Parser<'a, 'b> {
+    lines: &'a [Line<'a>],
+    cursor: usize,
+    declarations: &'b mut Vec<(String, Expr)>,
+    jumps: &'b mut Vec<(String, usize, usize)>,
+}
+
+impl Parser<'_, '_> {
+    /// Parses statements indented at least `indent`, stopping before a line that ends an
+    /// `<<if>>` branch.
+    fn block(&mut self, indent: usize) -> Result<Vec<Statement>, ParseError> {
+        let mut block = Vec::new();
+        while let Some(line) = self.lines.get(self.cursor) {
+            if line.indent < indent
+                || matches!(command_name(line.text), "elseif" | "else" | "endif")
+                    && line.text.starts_with("<<")
+            {
+                break;
+            }
+            self.cursor += 1;
+
+            if let Some(text) = line.text.strip_prefix("->") {
+                let option = self.option(line, text)?;
+                match block.last_mut() {
+                    Some(Statement::Options(options)) => options.push(option),
+                    _ => block.push(Statement::Options(vec![option])),
+                }
+                continue;
+            }
+            if line.text.starts_with("=>") {
+                return Err(line.error(0, YarnErrorKind::Unsupported("line groups".into())));
+            }
+
+            let statement = if line.text.starts_with("<<") {
+                let Some(statement) = self.command(line)? else {
+                    continue;
+                };
+                statement
+            } else {
+                let (text, condition) = split_condition(line, line.text)?;
+                let (speaker, text) = parse_line(line, text)?;
+                let statement = Statement::Line { speaker, text };
+                match condition {
+                    Some(condition) => {
+                        Statement::If(vec![(Some(condition), vec![statement].into())])
+                    }
+                    None => statement,
+                }
+            };
+            block.push(statement);
+        }
+        Ok(block)
+    }
+
+    fn option(&mut self, line: &Line, text: &str) -> Result<YarnOption, ParseError> {
+        let (text, condition) = split_condition(line, text)?;
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(line.error(0, YarnErrorKind::EmptyOption));
+        }
+        let (speaker, text) = parse_line(line, text)?;
+        if speaker.is_some() {
+            return Err(line.error(2, YarnErrorKind::Unsupported("speakers on options".into())));
+        }
+
+        let body = match self.lines.get(self.cursor) {
+            Some(next) if next.indent > line.indent => self.block(next.indent)?,
+            _ => Vec::new(),
+        };
+        Ok(YarnOption {
+            text,
+            condition: condition.map(Arc::new),
+            body: body.into(),
+        })
+    }
+
+    fn command(&mut self, line: &Line) -> Result<Option<Statement>, ParseError> {
+        let Some(command) = line
+            .text
+            .strip_prefix("<<")
+            .and_then(|text| text.strip_suffix(">>"))
+        else {
+            return Err(line.error(0, YarnErrorKind::UnclosedCommand));
+        };
+        let command = command.trim();
+        let name = command_name(command);
+        let args = command[name.len()..].trim();
+        let invalid = || line.error(0, YarnErrorKind::InvalidCommand(name.to_owned()));
+
+        Ok(Some(match name {
+            "jump" => {
+                if args.is_empty() || args.contains(char::is_whitespace) {
+                    return Err(invalid());
+                }
+                if args.starts_with('{') {
+                    return Err(
+                        line.error(7, YarnErrorKind::Unsupported("jumps to expressions".into()))
+                    );
+                }
+                self.jumps
+                    .push((args.to_owned(), line.number, line.indent + 8));
+                Statement::Jump(args.to_owned())
+            }
+            "stop" => Statement::Stop,
+            "set" | "declare" => {
+                let (variable, value) = assignment(args).ok_or_else(invalid)?;
+                let value = Expr::parse(value)
+                    .map_err(|error| line.error(0, YarnErrorKind::InvalidExpression(error)))?;
+                if name == "declare" {
+                    self.declarations.push((variable, value));
+                    return Ok(None);
+                }
+                Statement::Set {
+                    name: variable,
+                    value,
+                }
+            }
+            "if" => {
+                let mut branches = vec![(Some(self.expr(line, args)?), self.block(0)?.into())];
+                loop {
+                    let Some(next) = self.lines.get(self.cursor) else {
+                        return Err(line.error(0, YarnErrorKind::UnclosedIf));
+                    };
+                    self.cursor += 1;
+                    let text = next.text.trim_start_matches("<<").trim_end_matches(">>");
+                    match command_name(next.text) {
+                        "elseif" => {
+                            let condition = self.expr(next, text["elseif".len()..].trim())?;
+                            branches.push((Some(condition), self.block(0)?.into()));
+                        }
+                        "else" => branches.push((None, self.block(0)?.into())),
+                        _ => break,
+                    }
+                }
+                Statement::If(branches)
+            }
+            "call" | "detour" | "return" | "once" | "endonce" | "enum" | "case" | "endenum"
+            | "local" => {
+                return Err(line.error(
+                    2,
+                    YarnErrorKind::Unsupported(format!("`<<{name}>>` commands")),
+                ));
+            }
+            _ if name.is_empty() => return Err(invalid()),
+            _ => Statement::Command {
+                name: name.to_owned(),
+                args: args.split_whitespace().map(ToOwned::to_owned).collect(),
+            },
+        }))
+    }
+
+    fn expr(&self, line: &Line, text: &str) -> Result<Expr, ParseError> {
+        Expr::parse(text).map_err(|error| line.error(0, YarnErrorKind::InvalidExpression(error)))
+    }
+}
+
+/// Splits `$name to value` or `$name = value`.
+fn assignment(args: &str) -> Option<(String, &str)> {
+    let (name, value) = args.split_once(" to ").or_else(|| args.split_once('='))?;
+    let name = name.trim().strip_prefix('$')?;
+    (!name.is_empty()).then(|| (name.to_owned(), value.trim()))
+}
+
+/// Splits a trailing `<<if condition>>` from a line or option.
+fn split_condition<'a>(line: &Line, text: &'a str) -> Result<(&'a str, Option<Expr>), ParseError> {
+    let Some(start) = text.find("<<") else {
+        return Ok((text, None));
+    };
+    let offset = line.text.len() - text.len() + start;
+    let Some(condition) = text[start..]
+        .strip_prefix("<<if")
+        .and_then(|rest| rest.strip_suffix(">>"))
+    else {
+        return Err(line.error(offset, YarnErrorKind::Unsupported("inline commands".into())));
+    };
+    let condition = Expr::parse(condition)
+        .map_err(|error| line.error(offset, YarnErrorKind::InvalidExpression(error)))?;
+    Ok((text[..start].trim_end(), Some(condition)))
+}
+
+/// Parses `Speaker: text`, converting Yarn escapes and `{$variable}` interpolation to the
+/// syntax of [`Variables`].
+fn parse_line(line: &Line, text: &str) -> Result<(Option<String>, String), ParseError> {
+    let base = line.text.len() - text.len();
+    let (speaker, text, base) = match text.split_once(':') {
+        Some((speaker, rest))
+            if !speaker.trim().is_empty() && !speaker.contains(['\\', '{', '[', '<']) =>
+        {
+            let trimmed = rest.trim_start();
+            (
+                Some(speaker.trim().to_owned()),
+                trimmed,
+                base + speaker.len() + 1 + rest.len() - trimmed.len(),
+            )
+        }
+        _ => (None, text.trim(), base),
+    };
+
+    let mut result = String::with_capacity(text.len());
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next() {
+        match c {
+            '\\' => {
+                let escaped = text[i + 1..].chars().next();
+                match escaped {
+                    Some('{') => result.push_str("{{"),
+                    Some('}') => result.push_str("}}"),
+                    Some(c) => result.push(c),
+                    None => result.push('\\'),
+                }
+                i += 1 + escaped.map_or(0, char::len_utf8);
+                continue;
+            }
+            '{' => {
+                let Some(len) = text[i..].find('}') else {
+                    return Err(line.error(
+                        base + i,
+                        YarnErrorKind::InvalidExpression("unclosed `{`".into()),
+                    ));
+                };
+                match text[i + 1..i + len].trim().strip_prefix('$') {
+                    Some(name)
+                        if !name.is_empty()
+                            && name.chars().all(|c| c.is_alphanumeric() || c == '_') =>
+                    {
+                        result.push('{');
+                        result.push_str(name);
+                        result.push('}');
+                    }
+                    _ => {
+                        return Err(line.error(
+                            base + i,
+                            YarnErrorKind::Unsupported(
+                                "inline expressions other than variables".into(),
+                            ),
+                        ));
+                    }
+                }
+                i += len + 1;
+                continue;
+            }
+            '}' => result.push_str("}}"),
+            '[' => {
+                return Err(line.error(base + i, YarnErrorKind::Unsupported("markup tags".into())));
+            }
+            c => result.push(c),
+        }
+        i += c.len_utf8();
+    }
+    Ok((speaker, result))
+}
+
+#[derive(Debug)]
+enum Expr {
+    Value(Value),
+    Variable(String),
+    Not(Box<Expr>),
+    Negate(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Or,
+    Xor,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    fn precedence(self) -> u8 {
+        match self {
+            Self::Or | Self::Xor => 1,
+            Self::And => 2,
+            Self::Eq | Self::Ne => 3,
+            Self::Lt | Self::Le | Self::Gt | Self::Ge => 4,
+            Self::Add | Self::Sub => 5,
+            Self::Mul | Self::Div | Self::Rem => 6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Value(Value),
+    Variable(String),
+    Op(BinaryOp),
+    Not,
+    Open,
+    Close,
+}
+
+impl Expr {
+    fn parse(text: &str) -> Result<Self, String> {
+        let tokens = tokenize(text)?;
+        let mut cursor = 0;
+        let expr = Self::parse_binary(&tokens, &mut cursor, 0)?;
+        match tokens.get(cursor) {
+            Some(token) => Err(format!("unexpected {token:?}")),
+            None => Ok(expr),
+        }
+    }
+
+    fn parse_binary(tokens: &[Token], cursor: &mut usize, precedence: u8) -> Result<Self, String> {
+        let mut lhs = Self::parse_unary(tokens, cursor)?;
+        while let Some(Token::Op(op)) = tokens.get(*cursor) {
+            if op.precedence() < precedence {
+                break;
+            }
+            *cursor += 1;
+            let rhs = Self::parse_binary(tokens, cursor, op.precedence() + 1)?;
+            lhs = Self::Binary(*op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(tokens: &[Token], cursor: &mut usize) -> Result<Self, String> {
+        let token = tokens.get(*cursor).ok_or("expected a value")?;
+        *cursor += 1;
+        Ok(match token {
+            Token::Value(value) => Self::Value(value.clone()),
+            Token::Variable(name) => Self::Variable(name.clone()),
+            Token::Not => Self::Not(Box::new(Self::parse_unary(tokens, cursor)?)),
+            Token::Op(BinaryOp::Sub) => Self::Negate(Box::new(Self::parse_unary(tokens, cursor)?)),
+            Token::Open => {
+                let expr = Self::parse_binary(tokens, cursor, 0)?;
+                if tokens.get(*cursor) != Some(&Token::Close) {
+                    return Err("unclosed `(`".into());
+                }
+                *cursor += 1;
+                expr
+            }
+            token => return Err(format!("unexpected {token:?}")),
+        })
+    }
+
+    fn eval(&self, variables: &Variables, world: &World) -> Result<Value, String> {
+        Ok(match self {
+            Self::Value(value) => value.clone(),
+            Self::Variable(name) => variables
+                .resolve(name, world)
+                .ok_or_else(|| format!("unknown variable `${name}`"))?,
+            Self::Not(expr) => Value::Bool(!boolean(&expr.eval(variables, world)?)?),
+            Self::Negate(expr) => Value::Number(-number(&expr.eval(variables, world)?)?),
+            Self::Binary(op, lhs, rhs) => {
+                let lhs = lhs.eval(variables, world)?;
+                match op {
+                    BinaryOp::And if !boolean(&lhs)? => return Ok(Value::Bool(false)),
+                    BinaryOp::Or if boolean(&lhs)? => return Ok(Value::Bool(true)),
+                    _ => {}
+                }
+                binary(*op, lhs, rhs.eval(variables, world)?)?
+            }
+        })
+    }
+
+    /// Evaluates a condition, treating errors as false.
+    fn holds(&self, world: &World) -> bool {
+        let variables = world.resource::<Variables>();
+        match self
+            .eval(variables, world)
+            .and_then(|value| boolean(&value))
+        {
+            Ok(holds) => holds,
+            Err(error) => {
+                error!("{error} in yarn condition");
+                false
+            }
+        }
+    }
+}
+
+fn boolean(value: &Value) -> Result<bool, String> {
+    match value {
+        Value::Bool(value) => Ok(*value),
+        value => Err(format!("expected a boolean, found `{value}`")),
+    }
+}
+
+fn number(value: &Value) -> Result<f64, String> {
+    match value {
+        Value::Number(value) => Ok(*value),
+        value => Err(format!("expected a number, found `{value}`")),
+    }
+}
+
+fn binary(op: BinaryOp, lhs: Value, rhs: Value) -> Result<Value, String> {
+    Ok(match op {
+        BinaryOp::Eq => Value::Bool(lhs == rhs),
+        BinaryOp::Ne => Value::Bool(lhs != rhs),
+        BinaryOp::And | BinaryOp::Or => Value::Bool(boolean(&rhs)?),
+        BinaryOp::Xor => Value::Bool(boolean(&lhs)? != boolean(&rhs)?),
+        BinaryOp::Add => match (&lhs, &rhs) {
+            (Value::Number(lhs), Value::Number(rhs)) => Value::Number(lhs + rhs),
+            (Value::Text(_), _) | (_, Value::Text(_)) => Value::Text(format!("{lhs}{rhs}").into()),
+            _ => return Err(format!("cannot add `{lhs}` and `{rhs}`")),
+        },
+        _ => {
+            let (lhs, rhs) = (number(&lhs)?, number(&rhs)?);
+            match op {
+                BinaryOp::Lt => Value::Bool(lhs < rhs),
+                BinaryOp::Le => Value::Bool(lhs <= rhs),
+                BinaryOp::Gt => Value::Bool(lhs > rhs),
+                BinaryOp::Ge => Value::Bool(lhs >= rhs),
+                BinaryOp::Sub => Value::Number(lhs - rhs),
+                BinaryOp::Mul => Value::Number(lhs * rhs),
+                BinaryOp::Div => Value::Number(lhs / rhs),
+                _ => Value::Number(lhs % rhs),
+            }
+        }
+    })
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    let word = |start: usize| {
+        let len = chars[start..]
+            .iter()
+            .take_while(|c| c.is_alphanumeric() || **c == '_')
+            .count();
+        (
+            chars[start..start + len].iter().collect::<String>(),
+            start + len,
+        )
+    };
+
+    while let Some(&c) = chars.get(i) {
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c.is_ascii_digit() || c == '.' {
+            let len = chars[i..]
+                .iter()
+                .take_while(|c| c.is_ascii_digit() || **c == '.')
+                .count();
+            let number = chars[i..i + len].iter().collect::<String>();
+            let number = number
+                .parse()
+                .map_err(|_| format!("invalid number `{number}`"))?;
+            tokens.push(Token::Value(Value::Number(number)));
+            i += len;
+        } else if c == '"' {
+            let mut string = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    Some('"') => break,
+                    Some('\\') if i + 1 < chars.len() => {
+                        string.push(chars[i + 1]);
+                        i += 2;
+                    }
+                    Some(c) => {
+                        string.push(*c);
+                        i += 1;
+                    }
+                    None => return Err("unclosed string".into()),
+                }
+            }
+            tokens.push(Token::Value(Value::Text(string.into())));
+            i += 1;
+        } else if c == '$' {
+            let (name, end) = word(i + 1);
+            if name.is_empty() {
+                return Err("expected a variable name after `$`".into());
+            }
+            tokens.push(Token::Variable(name));
+            i = end;
+        } else if c.is_alphabetic() || c == '_' {
+            let (name, end) = word(i);
+            if chars[end..].iter().find(|c| !c.is_whitespace()) == Some(&'(') {
+                return Err(format!("function calls such as `{name}` are not supported"));
+            }
+            tokens.push(match name.as_str() {
+                "true" => Token::Value(Value::Bool(true)),
+                "false" => Token::Value(Value::Bool(false)),
+                "not" => Token::Not,
+                "and" => Token::Op(BinaryOp::And),
+                "or" => Token::Op(BinaryOp::Or),
+                "xor" => Token::Op(BinaryOp::Xor),
+                "is" | "eq" => Token::Op(BinaryOp::Eq),
+                "neq" => Token::Op(BinaryOp::Ne),
+                "lt" => Token::Op(BinaryOp::Lt),
+                "lte" => Token::Op(BinaryOp::Le),
+                "gt" => Token::Op(BinaryOp::Gt),
+                "gte" => Token::Op(BinaryOp::Ge),
+                _ => return Err(format!("unknown word `{name}`")),
+            });
+            i = end;
+        } else {
+            let pair = chars[i..].iter().take(2).collect::<String>();
+            let (token, len) = match pair.as_str() {
+                "==" => (Token::Op(BinaryOp::Eq), 2),
+                "!=" => (Token::Op(BinaryOp::Ne), 2),
+                "<=" => (Token::Op(BinaryOp::Le), 2),
+                ">=" => (Token::Op(BinaryOp::Ge), 2),
+                "&&" => (Token::Op(BinaryOp::And), 2),
+                "||" => (Token::Op(BinaryOp::Or), 2),
+                _ => match c {
+                    '<' => (Token::Op(BinaryOp::Lt), 1),
+                    '>' => (Token::Op(BinaryOp::Gt), 1),
+                    '+' => (Token::Op(BinaryOp::Add), 1),
+                    '-' => (Token::Op(BinaryOp::Sub), 1),
+                    '*' => (Token::Op(BinaryOp::Mul), 1),
+                    '/' => (Token::Op(BinaryOp::Div), 1),
+                    '%' => (Token::Op(BinaryOp::Rem), 1),
+                    '^' => (Token::Op(BinaryOp::Xor), 1),
+                    '!' => (Token::Not, 1),
+                    '(' => (Token::Open, 1),
+                    ')' => (Token::Close, 1),
+                    c => return Err(format!("unexpected `{c}`")),
+                },
+            };
+            tokens.push(token);
+            i += len;
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node<'a>(project: &'a YarnProject, title: &str) -> &'a [Statement] {
+        &project.nodes[title]
+    }
+
+    fn kind(source: &str) -> YarnErrorKind {
+        parse_project(source).unwrap_err().2
+    }
+
+    #[test]
+    fn declarations_are_collected_once() {
+        let project = parse_project(
+            "title: Start\n---\n<<declare $gold = 5>>\nHello.\n<<set $gold to 6>>\n===\n",
+        )
+        .unwrap();
+
+        assert_eq!(project.declarations.len(), 1);
+        assert_eq!(project.declarations[0].0, "gold");
+        assert!(matches!(
+            &project.declarations[0].1,
+            Expr::Value(value) if *value == Value::Number(5.)
+        ));
+
+        let statements = node(&project, "Start");
+        assert_eq!(statements.len(), 2);
+        assert!(matches!(statements[0], Statement::Line { .. }));
+        assert!(matches!(&statements[1], Statement::Set { name, .. } if name == "gold"));
+    }
+
+    #[test]
+    fn lines_with_speakers_and_variables() {
+        let project =
+            parse_project("title: Start\n---\nNic: Hello, {$name}! // greeting\n===\n").unwrap();
+        let Statement::Line { speaker, text } = &node(&project, "Start")[0] else {
+            panic!("expected a line");
+        };
+        assert_eq!(speaker.as_deref(), Some("Nic"));
+        assert_eq!(text, "Hello, {name}!");
+    }
+
+    #[test]
+    fn consecutive_options_form_one_choice() {
+        let project = parse_project(
+            "title: Start\n---\n-> Yes\n    Great.\n-> No <<if $brave>>\nDone.\n===\n",
+        )
+        .unwrap();
+        let statements = node(&project, "Start");
+        assert_eq!(statements.len(), 2);
+        let Statement::Options(options) = &statements[0] else {
+            panic!("expected options");
+        };
+        assert_eq!(options.len(), 2);
+        assert_eq!(options[0].text, "Yes");
+        assert_eq!(options[0].body.len(), 1);
+        assert!(options[0].condition.is_none());
+        assert_eq!(options[1].text, "No");
+        assert!(options[1].body.is_empty());
+        assert!(options[1].condition.is_some());
+    }
+
+    #[test]
+    fn statements_after_the_last_line_do_not_wait() {
+        let project = parse_project(concat!(
+            "title: Start\n---\nHello.\n<<set $met to true>>\n<<wave>>\n",
+            "<<if $met>>\n    <<set $gold to 1>>\n<<endif>>\n<<jump End>>\n===\n",
+            "title: End\n---\n<<fade>>\n===\n",
+            "title: Loop\n---\nHi.\n<<if $again>>\n    Again.\n<<endif>>\n===\n",
+        ))
+        .unwrap();
+        let frames = |title: &str, index| vec![(project.nodes[title].clone(), index)];
+
+        assert!(waits(&project.nodes, &frames("Start", 0)));
+        assert!(!waits(&project.nodes, &frames("Start", 1)));
+        assert!(waits(&project.nodes, &frames("Loop", 1)));
+        assert!(!waits(&project.nodes, &frames("Loop", 2)));
+    }
+
+    #[test]
+    fn if_branches() {
+        let project = parse_project(
+            "title: Start\n---\n<<if $a>>\nA\n<<elseif $b>>\nB\n<<else>>\nC\n<<endif>>\n===\n",
+        )
+        .unwrap();
+        let Statement::If(branches) = &node(&project, "Start")[0] else {
+            panic!("expected an if");
+        };
+        assert_eq!(branches.len(), 3);
+        assert!(branches[2].0.is_none());
+    }
+
+    #[test]
+    fn jumps_and_commands() {
+        let project = parse_project(
+            "title: Start\n---\n<<shake 2 fast>>\n<<jump End>>\n===\ntitle: End\n---\nBye.\n===\n",
+        )
+        .unwrap();
+        let statements = node(&project, "Start");
+        assert!(matches!(
+            &statements[0],
+            Statement::Command { name, args } if name == "shake" && args == &["2", "fast"]
+        ));
+        assert!(matches!(&statements[1], Statement::Jump(node) if node == "End"));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            kind("title: Start\n---\n<<jump Nowhere>>\n===\n"),
+            YarnErrorKind::UnknownNode("Nowhere".into())
+        );
+        assert_eq!(
+            kind("title: Start\n---\n<<if $a>>\nA\n===\n"),
+            YarnErrorKind::UnclosedIf
+        );
+        assert_eq!(kind("---\nA\n===\n"), YarnErrorKind::MissingTitle);
+        assert_eq!(
+            kind("title: Start\n---\nA\n"),
+            YarnErrorKind::UnterminatedNode
+        );
+        assert_eq!(
+            kind("title: Start\n---\n<<endif>>\n===\n"),
+            YarnErrorKind::UnexpectedCommand("endif".into())
+        );
+        assert_eq!(
+            kind("title: Start\n---\nHello [b]there[/b]\n===\n"),
+            YarnErrorKind::Unsupported("markup tags".into())
+        );
+        assert_eq!(
+            kind("title: A\n---\nA\n===\ntitle: A\n---\nB\n===\n"),
+            YarnErrorKind::DuplicateNode("A".into())
+        );
+    }
+
+    #[test]
+    fn expressions() {
+        let mut world = World::new();
+        world.insert_resource(Variables::default().with("gold", 10));
+        let holds = |text: &str| Expr::parse(text).unwrap().holds(&world);
+
+        assert!(holds("1 + 2 * 3 == 7"));
+        assert!(holds("$gold >= 10 and not false"));
+        assert!(holds("($gold - 4) / 2 is 3"));
+        assert!(holds("\"a\" + \"b\" == \"ab\""));
+        assert!(!holds("$gold < 5 || false"));
+        assert!(Expr::parse("visited(\"Start\")").is_err());
+    }
+}